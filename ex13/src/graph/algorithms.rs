@@ -0,0 +1,407 @@
+use super::{Graph, ShortestPaths};
+
+use std::collections::{BinaryHeap, VecDeque};
+use std::vec::Vec;
+
+/// Directed graph with weighted arcs, independent of road data.
+///
+/// Nodes are numbered from zero up to the number of nodes, arcs are
+/// identified by ids which are only used to look up their weights.
+pub trait WeightedGraph {
+	/// Returns the number of nodes.
+	fn num_nodes(&self) -> usize;
+
+	/// Returns the head node and arc id of the outgoing arcs of the node.
+	fn neighbors(&self, node: usize) -> Vec<(usize, usize)>;
+
+	/// Returns the weight of the arc, which may be negative.
+	fn arc_weight(&self, arc: usize) -> f64;
+}
+
+impl WeightedGraph for Graph {
+	fn num_nodes(&self) -> usize {
+		self.nodes.len()
+	}
+
+	fn neighbors(&self, node: usize) -> Vec<(usize, usize)> {
+		self.arcs(node).map(|arc| (self.arc_heads[arc], arc)).collect()
+	}
+
+	/// The arc costs of the current metric.
+	fn arc_weight(&self, arc: usize) -> f64 {
+		self.arc_costs[arc]
+	}
+}
+
+/// Graph built in memory from a list of arcs.
+pub struct AdjacencyList {
+	neighbors: Vec<Vec<(usize, usize)>>,
+	weights: Vec<f64>
+}
+
+impl AdjacencyList {
+
+	/// Create a graph with the given number of nodes and no arcs.
+	pub fn new(node_count: usize) -> AdjacencyList {
+		AdjacencyList {
+			neighbors: vec![Vec::new(); node_count],
+			weights: Vec::new()
+		}
+	}
+
+	/// Create a graph from a list of arcs given as tail node, head node and weight.
+	pub fn from_arcs(node_count: usize, arcs: &[(usize, usize, f64)]) -> AdjacencyList {
+		let mut graph = AdjacencyList::new(node_count);
+		for &(tail_node, head_node, weight) in arcs.iter() {
+			graph.add_arc(tail_node, head_node, weight);
+		}
+
+		return graph;
+	}
+
+	/// Add an arc and return its id.
+	pub fn add_arc(&mut self, tail_node: usize, head_node: usize, weight: f64) -> usize {
+		assert!(tail_node < self.neighbors.len() && head_node < self.neighbors.len(), "Invalid arc node!");
+
+		let arc = self.weights.len();
+		self.neighbors[tail_node].push((head_node, arc));
+		self.weights.push(weight);
+		return arc;
+	}
+}
+
+impl WeightedGraph for AdjacencyList {
+	fn num_nodes(&self) -> usize {
+		self.neighbors.len()
+	}
+
+	fn neighbors(&self, node: usize) -> Vec<(usize, usize)> {
+		self.neighbors[node].clone()
+	}
+
+	fn arc_weight(&self, arc: usize) -> f64 {
+		self.weights[arc]
+	}
+}
+
+/// Returns the number of arcs on a path with the fewest arcs from the start
+/// node to each node, or None for nodes which are not reachable.
+pub fn breadth_first_search<G: WeightedGraph>(graph: &G, start_node: usize) -> Vec<Option<usize>> {
+	let mut levels = vec![None; graph.num_nodes()];
+	let mut queue = VecDeque::new();
+
+	levels[start_node] = Some(0);
+	queue.push_back(start_node);
+
+	while let Some(node) = queue.pop_front() {
+		let level = levels[node].unwrap();
+		for (head_node, _) in graph.neighbors(node) {
+			if levels[head_node].is_none() {
+				levels[head_node] = Some(level + 1);
+				queue.push_back(head_node);
+			}
+		}
+	}
+
+	return levels;
+}
+
+/// Returns the nodes reachable from the start node in the order a depth
+/// first search visits them, following the arcs in their given order.
+pub fn depth_first_search<G: WeightedGraph>(graph: &G, start_node: usize) -> Vec<usize> {
+	let mut visited = vec![false; graph.num_nodes()];
+	let mut order = vec![start_node];
+
+	// Nodes of the current path with the arcs not followed yet
+	let mut stack = vec![(start_node, graph.neighbors(start_node), 0)];
+	visited[start_node] = true;
+
+	while let Some((node, neighbors, index)) = stack.pop() {
+		if index >= neighbors.len() {
+			continue;
+		}
+
+		let head_node = neighbors[index].0;
+		stack.push((node, neighbors, index + 1));
+
+		if !visited[head_node] {
+			visited[head_node] = true;
+			order.push(head_node);
+			stack.push((head_node, graph.neighbors(head_node), 0));
+		}
+	}
+
+	return order;
+}
+
+/// Returns the nodes ordered such that all arcs lead to later nodes, or
+/// None if the graph contains a cycle.
+///
+/// Uses Kahn's algorithm, nodes without order between them stay in
+/// ascending order where possible.
+pub fn topological_sort<G: WeightedGraph>(graph: &G) -> Option<Vec<usize>> {
+	let node_count = graph.num_nodes();
+
+	let mut in_degrees = vec![0; node_count];
+	for node in 0..node_count {
+		for (head_node, _) in graph.neighbors(node) {
+			in_degrees[head_node] += 1;
+		}
+	}
+
+	let mut queue: VecDeque<usize> = (0..node_count).filter(|&node| in_degrees[node] == 0).collect();
+	let mut order = Vec::with_capacity(node_count);
+
+	while let Some(node) = queue.pop_front() {
+		order.push(node);
+		for (head_node, _) in graph.neighbors(node) {
+			in_degrees[head_node] -= 1;
+			if in_degrees[head_node] == 0 {
+				queue.push_back(head_node);
+			}
+		}
+	}
+
+	// The nodes of a cycle never lose all incoming arcs
+	match order.len() == node_count {
+		true => Some(order),
+		false => None
+	}
+}
+
+/// Compute the shortest paths from the start node with the Bellman-Ford algorithm.
+///
+/// Unlike Dijkstra's algorithm this allows negative arc weights. Returns
+/// None if a cycle with negative weight is reachable from the start node.
+/// All reachable nodes are settled.
+pub fn bellman_ford<G: WeightedGraph>(graph: &G, start_node: usize) -> Option<ShortestPaths> {
+	let mut paths = ShortestPaths::new(graph.num_nodes(), start_node);
+	if !relax_arcs(graph, &mut paths.distances, &mut paths.traceback_arcs) {
+		return None;
+	}
+
+	for node in 0..graph.num_nodes() {
+		paths.settled[node] = paths.distances[node].is_some();
+	}
+	paths.num_settled = paths.settled.iter().filter(|&&settled| settled).count();
+
+	return Some(paths);
+}
+
+/// Compute the costs of the shortest paths between all nodes with the
+/// Floyd-Warshall algorithm.
+///
+/// The result contains a row for each start node with the costs to each
+/// end node, or None if the end node is not reachable. Returns None if the
+/// graph contains a cycle with negative weight. Needs quadratic memory and
+/// cubic time in the number of nodes.
+pub fn floyd_warshall<G: WeightedGraph>(graph: &G) -> Option<Vec<Vec<Option<f64>>>> {
+	let node_count = graph.num_nodes();
+
+	let mut distances = vec![vec![None; node_count]; node_count];
+	for node in 0..node_count {
+		distances[node][node] = Some(0.0);
+		for (head_node, arc) in graph.neighbors(node) {
+			let weight = graph.arc_weight(arc);
+			match distances[node][head_node] {
+				Some(distance) if distance <= weight => { },
+				_ => { distances[node][head_node] = Some(weight); }
+			}
+		}
+	}
+
+	for via_node in 0..node_count {
+		for start_node in 0..node_count {
+			let first = match distances[start_node][via_node] {
+				Some(first) => first,
+				None => { continue; }
+			};
+
+			for end_node in 0..node_count {
+				if let Some(second) = distances[via_node][end_node] {
+					match distances[start_node][end_node] {
+						Some(distance) if distance <= first + second => { },
+						_ => { distances[start_node][end_node] = Some(first + second); }
+					}
+				}
+			}
+		}
+	}
+
+	// A node on a negative cycle can reach itself with negative costs
+	match (0..node_count).any(|node| distances[node][node].unwrap() < 0.0) {
+		true => None,
+		false => Some(distances)
+	}
+}
+
+/// Compute the costs of the shortest paths between all nodes with Johnson's algorithm.
+///
+/// The arc weights are made non-negative with node potentials from the
+/// Bellman-Ford algorithm, then Dijkstra's algorithm runs from every node.
+/// This is faster than Floyd-Warshall for sparse graphs. The result is the
+/// same as for the Floyd-Warshall algorithm.
+pub fn johnson<G: WeightedGraph>(graph: &G) -> Option<Vec<Vec<Option<f64>>>> {
+	let node_count = graph.num_nodes();
+
+	// Same as a search from an additional node with arcs of weight zero to all nodes
+	let mut potentials = vec![Some(0.0); node_count];
+	let mut traceback_arcs = vec![None; node_count];
+	if !relax_arcs(graph, &mut potentials, &mut traceback_arcs) {
+		return None;
+	}
+	let potentials: Vec<f64> = potentials.into_iter().map(|potential| potential.unwrap()).collect();
+
+	let rows = (0..node_count).map(|start_node| {
+		dijkstra(graph, start_node, |tail_node, head_node, arc| {
+			// Rounding errors may leave tiny negative weights
+			(graph.arc_weight(arc) + potentials[tail_node] - potentials[head_node]).max(0.0)
+		}).into_iter()
+			.enumerate()
+			.map(|(end_node, distance)| distance.map(|distance| distance - potentials[start_node] + potentials[end_node]))
+			.collect()
+	}).collect();
+
+	return Some(rows);
+}
+
+/// Relax all arcs until no distance changes, as in the Bellman-Ford algorithm.
+///
+/// Returns false if the distances still change after as many rounds as
+/// there are nodes, then a cycle with negative weight is reachable.
+fn relax_arcs<G: WeightedGraph>(graph: &G, distances: &mut Vec<Option<f64>>,
+		traceback_arcs: &mut Vec<Option<(usize, usize)>>) -> bool {
+	for _ in 0..graph.num_nodes() {
+		let mut changed = false;
+
+		for node in 0..graph.num_nodes() {
+			let node_distance = match distances[node] {
+				Some(distance) => distance,
+				None => { continue; }
+			};
+
+			for (head_node, arc) in graph.neighbors(node) {
+				let next_distance = node_distance + graph.arc_weight(arc);
+				match distances[head_node] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+
+				distances[head_node] = Some(next_distance);
+				traceback_arcs[head_node] = Some((node, arc));
+				changed = true;
+			}
+		}
+
+		if !changed {
+			return true;
+		}
+	}
+
+	return graph.num_nodes() == 0;
+}
+
+/// Run Dijkstra's algorithm with non-negative weights given by tail node, head node and arc.
+fn dijkstra<G, W>(graph: &G, start_node: usize, weight: W) -> Vec<Option<f64>>
+		where G: WeightedGraph, W: Fn(usize, usize, usize) -> f64 {
+	let mut distances = vec![None; graph.num_nodes()];
+	let mut settled = vec![false; graph.num_nodes()];
+
+	let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+	distances[start_node] = Some(0.0);
+	active_nodes.push((0, start_node));
+
+	while let Some((_, node)) = active_nodes.pop() {
+		if settled[node] {
+			continue;
+		}
+
+		settled[node] = true;
+
+		let node_distance = distances[node].unwrap();
+		for (head_node, arc) in graph.neighbors(node) {
+			if settled[head_node] {
+				continue;
+			}
+
+			let next_distance = node_distance + weight(node, head_node, arc);
+			match distances[head_node] {
+				Some(distance) if distance <= next_distance => { continue; },
+				_ => { }
+			}
+
+			distances[head_node] = Some(next_distance);
+
+			// Use negative numbers since we only have a max-heap
+			active_nodes.push((-(next_distance * 1000.0) as i64, head_node));
+		}
+	}
+
+	return distances;
+}
+
+#[test]
+fn test_traversal() {
+	let graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	assert_eq!(vec![Some(0), Some(1), Some(1), Some(2), None], breadth_first_search(&graph, 0));
+	assert_eq!(vec![None, Some(2), Some(3), Some(1), Some(0)], breadth_first_search(&graph, 4));
+	assert_eq!(vec![0, 1, 2, 3], depth_first_search(&graph, 0));
+	assert_eq!(vec![4, 3, 1, 2], depth_first_search(&graph, 4));
+
+	// The arcs between 1, 2 and 3 form a cycle
+	assert_eq!(None, topological_sort(&graph));
+
+	// Dependencies of a build: 0 before 2, 1 before 2, 2 before 3 and 4, 1 before 4
+	let dependencies = AdjacencyList::from_arcs(5, &[(0, 2, 1.0), (1, 2, 1.0), (2, 3, 1.0), (2, 4, 1.0), (1, 4, 1.0)]);
+	assert_eq!(Some(vec![0, 1, 2, 3, 4]), topological_sort(&dependencies));
+	assert_eq!(vec![1, 2, 3, 4], depth_first_search(&dependencies, 1));
+
+	let dependencies = AdjacencyList::from_arcs(3, &[(2, 0, 1.0), (0, 1, 1.0)]);
+	assert_eq!(Some(vec![2, 0, 1]), topological_sort(&dependencies));
+	assert_eq!(Some(Vec::new()), topological_sort(&AdjacencyList::new(0)));
+}
+
+#[test]
+fn test_shortest_paths_on_road_graph() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let floyd_warshall = floyd_warshall(&graph).unwrap();
+	let johnson = johnson(&graph).unwrap();
+
+	for start_node in 0..graph.num_nodes() {
+		let dijkstra = graph.compute_shortest_paths(start_node);
+		let bellman_ford = bellman_ford(&graph, start_node).unwrap();
+
+		assert_eq!(dijkstra.distances(), bellman_ford.distances());
+		assert_eq!(dijkstra.distances(), &floyd_warshall[start_node]);
+		assert_eq!(dijkstra.distances(), &johnson[start_node]);
+	}
+
+	let paths = bellman_ford(&graph, 0).unwrap();
+	assert_eq!(Some(Box::new(vec![0, 2, 5])), paths.path(5));
+	assert_eq!(None, paths.path(6));
+	assert_eq!(6, paths.num_settled());
+}
+
+#[test]
+fn test_negative_weights() {
+	let mut graph = AdjacencyList::from_arcs(5, &[
+		(0, 1, 4.0), (0, 2, 2.0), (2, 1, -3.0), (1, 3, 2.0), (3, 4, -1.0), (2, 4, 5.0)
+	]);
+
+	let paths = bellman_ford(&graph, 0).unwrap();
+	assert_eq!(&vec![Some(0.0), Some(-1.0), Some(2.0), Some(1.0), Some(0.0)], paths.distances());
+	assert_eq!(Some(Box::new(vec![0, 2, 1, 3, 4])), paths.path(4));
+
+	let expected = floyd_warshall(&graph).unwrap();
+	assert_eq!(expected, johnson(&graph).unwrap());
+	assert_eq!(vec![None, Some(-3.0), Some(0.0), Some(-1.0), Some(-2.0)], expected[2]);
+	assert_eq!(vec![None, None, None, None, Some(0.0)], expected[4]);
+
+	// Negative cycle 1 -> 3 -> 1, which node 4 cannot reach
+	graph.add_arc(3, 1, -2.5);
+	assert!(bellman_ford(&graph, 0).is_none());
+	assert!(bellman_ford(&graph, 4).is_some());
+	assert!(floyd_warshall(&graph).is_none());
+	assert!(johnson(&graph).is_none());
+}
\ No newline at end of file
@@ -0,0 +1,283 @@
+use super::Graph;
+
+use std::collections::{BinaryHeap, HashSet};
+use std::vec::Vec;
+
+/// Factor for the costs of arcs each time they are part of a found route.
+const PENALTY_FACTOR: f64 = 1.4;
+/// Maximum ratio between the costs of an alternative and the primary route.
+const MAX_STRETCH: f64 = 1.5;
+/// Maximum number of penalized searches per requested route.
+const MAX_ITERATIONS: usize = 4;
+
+/// Loopless path given by its arcs.
+struct ArcPath {
+	cost: f64,
+	arcs: Vec<usize>
+}
+
+impl Graph {
+
+	/// Compute up to k shortest loopless paths between two nodes with Yen's algorithm.
+	///
+	/// Each path runs a Dijkstra search per node on it, so this is meant for small
+	/// graphs. The result contains the distance, the travel time with the given
+	/// max speed and the nodes of each path, ordered by ascending costs.
+	pub fn compute_k_shortest_paths(&self, start_node: usize, end_node: usize, k: usize,
+			max_speed: u64) -> Vec<(f64, f64, Box<Vec<usize>>)> {
+		if k == 0 {
+			return Vec::new();
+		}
+
+		let mut paths: Vec<ArcPath> = Vec::new();
+		let mut candidates: Vec<ArcPath> = Vec::new();
+		let mut banned_nodes = vec![false; self.num_nodes()];
+		let mut banned_heads = HashSet::new();
+
+		match self.search_path(start_node, end_node, |_| true, |arc| self.arc_costs[arc]) {
+			Some(path) => { paths.push(path); },
+			None => { return Vec::new(); }
+		}
+
+		while paths.len() < k {
+			{
+				let previous = &paths[paths.len() - 1];
+				let mut spur_node = start_node;
+				let mut root_cost = 0.0;
+
+				for index in 0..previous.arcs.len() {
+					let root = &previous.arcs[..index];
+
+					// Do not go to the next node of any found path with the same root,
+					// also not over parallel arcs
+					banned_heads.clear();
+					for path in paths.iter() {
+						if path.arcs.len() > index && &path.arcs[..index] == root {
+							banned_heads.insert(self.arc_heads[path.arcs[index]]);
+						}
+					}
+
+					let spur_arcs = self.arcs(spur_node);
+					let spur = self.search_path(spur_node, end_node,
+						|arc| !(spur_arcs.contains(&arc) && banned_heads.contains(&self.arc_heads[arc]))
+							&& !banned_nodes[self.arc_heads[arc]],
+						|arc| self.arc_costs[arc]);
+
+					if let Some(spur) = spur {
+						let mut arcs = root.to_vec();
+						arcs.extend(spur.arcs);
+
+						let candidate = ArcPath { cost: root_cost + spur.cost, arcs: arcs };
+						if !candidates.iter().chain(paths.iter()).any(|path| path.arcs == candidate.arcs) {
+							candidates.push(candidate);
+						}
+					}
+
+					// The root path may not be visited again
+					banned_nodes[spur_node] = true;
+					root_cost += self.arc_costs[previous.arcs[index]];
+					spur_node = self.arc_heads[previous.arcs[index]];
+				}
+			}
+
+			for banned in banned_nodes.iter_mut() {
+				*banned = false;
+			}
+
+			let cheapest = candidates.iter()
+				.enumerate()
+				.min_by(|first, second| (first.1.cost, first.0).partial_cmp(&(second.1.cost, second.0)).unwrap())
+				.map(|(index, _)| index);
+			match cheapest {
+				Some(index) => { paths.push(candidates.swap_remove(index)); },
+				None => { break; }
+			}
+		}
+
+		paths.iter().map(|path| self.arc_path_to_result(start_node, path, max_speed)).collect()
+	}
+
+	/// Compute alternative routes between two nodes with the penalty method.
+	///
+	/// Repeated searches penalize the arcs of the routes found so far. A route
+	/// becomes an alternative if at most the given ratio of the primary route
+	/// distance is shared with it and it is not much longer than the primary
+	/// route. The result contains up to count routes, the primary route first,
+	/// with their distance, travel time with the given max speed and nodes.
+	pub fn compute_alternative_paths(&self, start_node: usize, end_node: usize, count: usize,
+			max_overlap: f64, max_speed: u64) -> Vec<(f64, f64, Box<Vec<usize>>)> {
+		if count == 0 {
+			return Vec::new();
+		}
+
+		let primary = match self.search_path(start_node, end_node, |_| true, |arc| self.arc_costs[arc]) {
+			Some(primary) => primary,
+			None => { return Vec::new(); }
+		};
+
+		let primary_arcs: HashSet<usize> = primary.arcs.iter().cloned().collect();
+		let primary_distance: f64 = primary.arcs.iter().map(|&arc| self.arc_distances[arc] as f64).sum();
+
+		let mut penalties = vec![1.0; self.num_arcs()];
+		let mut paths = vec![primary];
+
+		for _ in 0..(count * MAX_ITERATIONS) {
+			if paths.len() >= count {
+				break;
+			}
+
+			for &arc in paths[paths.len() - 1].arcs.iter() {
+				penalties[arc] *= PENALTY_FACTOR;
+			}
+
+			let mut path = match self.search_path(start_node, end_node, |_| true, |arc| self.arc_costs[arc] * penalties[arc]) {
+				Some(path) => path,
+				None => { break; }
+			};
+			path.cost = path.arcs.iter().map(|&arc| self.arc_costs[arc]).sum();
+
+			let shared_distance: f64 = path.arcs.iter()
+				.filter(|arc| primary_arcs.contains(arc))
+				.map(|&arc| self.arc_distances[arc] as f64)
+				.sum();
+
+			let overlap = match primary_distance > 0.0 {
+				true => shared_distance / primary_distance,
+				false => 1.0
+			};
+
+			if overlap <= max_overlap && path.cost <= MAX_STRETCH * paths[0].cost
+					&& !paths.iter().any(|other| other.arcs == path.arcs) {
+				paths.push(path);
+			} else {
+				// Penalize the rejected route too, to find a different one next time
+				for &arc in path.arcs.iter() {
+					penalties[arc] *= PENALTY_FACTOR;
+				}
+			}
+		}
+
+		paths.iter().map(|path| self.arc_path_to_result(start_node, path, max_speed)).collect()
+	}
+
+	/// Run Dijkstra's algorithm over the allowed arcs with the given arc costs.
+	fn search_path<A, C>(&self, start_node: usize, end_node: usize, allowed: A, cost: C) -> Option<ArcPath>
+			where A: Fn(usize) -> bool, C: Fn(usize) -> f64 {
+		let mut distances: Vec<Option<f64>> = vec![None; self.num_nodes()];
+		let mut settled = vec![false; self.num_nodes()];
+		let mut traceback_arcs: Vec<Option<(usize, usize)>> = vec![None; self.num_nodes()];
+
+		let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+		distances[start_node] = Some(0.0);
+		active_nodes.push((0, start_node));
+
+		while let Some((_, node)) = active_nodes.pop() {
+			if settled[node] {
+				continue;
+			}
+
+			settled[node] = true;
+			if node == end_node {
+				break;
+			}
+
+			let node_distance = distances[node].unwrap();
+			for arc in self.arcs(node) {
+				let head_node = self.arc_heads[arc];
+				if settled[head_node] || !allowed(arc) {
+					continue;
+				}
+
+				let next_distance = node_distance + cost(arc);
+				match distances[head_node] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+
+				distances[head_node] = Some(next_distance);
+				traceback_arcs[head_node] = Some((node, arc));
+
+				// Use negative numbers since we only have a max-heap
+				active_nodes.push((-(next_distance * 1000.0) as i64, head_node));
+			}
+		}
+
+		if !settled[end_node] {
+			return None;
+		}
+
+		let mut arcs = Vec::new();
+		let mut node = end_node;
+		while let Some((tail_node, arc)) = traceback_arcs[node] {
+			arcs.push(arc);
+			node = tail_node;
+		}
+		arcs.reverse();
+
+		return Some(ArcPath { cost: distances[end_node].unwrap(), arcs: arcs });
+	}
+
+	fn arc_path_to_result(&self, start_node: usize, path: &ArcPath, max_speed: u64) -> (f64, f64, Box<Vec<usize>>) {
+		let mut nodes = Box::new(vec![start_node]);
+		let mut distance = 0.0;
+		let mut time = 0.0;
+
+		for &arc in path.arcs.iter() {
+			distance += self.arc_distances[arc] as f64;
+			time += self.arc_travel_time(arc, max_speed);
+			nodes.push(self.arc_heads[arc]);
+		}
+
+		return (distance, time, nodes);
+	}
+}
+
+#[test]
+fn test_k_shortest_paths() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let paths = graph.compute_k_shortest_paths(0, 5, 4, 130);
+	assert_eq!(4, paths.len());
+	assert_eq!((3650.0, vec![0, 2, 5]), (paths[0].0, *paths[0].2.clone()));
+	assert_eq!((3700.0, vec![0, 3, 5]), (paths[1].0, *paths[1].2.clone()));
+	assert_eq!(4200.0, paths[2].0);
+	assert_eq!(4200.0, paths[3].0);
+
+	// All loopless paths, each only once and ordered by distance
+	let paths = graph.compute_k_shortest_paths(0, 5, 100, 130);
+	assert!(paths.len() > 4 && paths.len() < 100);
+	for (index, path) in paths.iter().enumerate() {
+		let mut nodes = path.2.clone();
+		nodes.sort();
+		nodes.dedup();
+		assert_eq!(path.2.len(), nodes.len());
+		assert!(index == 0 || paths[index - 1].0 <= path.0);
+		assert!(paths[..index].iter().all(|other| other.2 != path.2));
+	}
+
+	assert!(graph.compute_k_shortest_paths(5, 6, 3, 130).is_empty());
+	assert!(graph.compute_k_shortest_paths(0, 5, 0, 130).is_empty());
+	assert_eq!(vec![(0.0, 0.0, Box::new(vec![2]))], graph.compute_k_shortest_paths(2, 2, 3, 130));
+}
+
+#[test]
+fn test_alternative_paths() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let paths = graph.compute_alternative_paths(0, 5, 2, 0.5, 130);
+	assert_eq!(vec![vec![0, 2, 5], vec![0, 3, 5]],
+		paths.iter().map(|path| *path.2.clone()).collect::<Vec<Vec<usize>>>());
+
+	// No arc in common with the primary route
+	let paths = graph.compute_alternative_paths(0, 5, 3, 0.0, 130);
+	assert_eq!(vec![vec![0, 2, 5], vec![0, 3, 5], vec![0, 1, 4, 5]],
+		paths.iter().map(|path| *path.2.clone()).collect::<Vec<Vec<usize>>>());
+
+	// Only slightly longer routes are alternatives
+	let paths = graph.compute_alternative_paths(0, 1, 3, 1.0, 130);
+	assert_eq!(vec![vec![0, 1]],
+		paths.iter().map(|path| *path.2.clone()).collect::<Vec<Vec<usize>>>());
+
+	assert!(graph.compute_alternative_paths(5, 6, 3, 0.5, 130).is_empty());
+	assert!(graph.compute_alternative_paths(0, 5, 0, 0.5, 130).is_empty());
+}
\ No newline at end of file
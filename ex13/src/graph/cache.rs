@@ -0,0 +1,344 @@
+use super::{Error, Graph, LoadMode, Node, Progress};
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Result as IOResult, Write};
+use std::result::Result;
+use std::time::UNIX_EPOCH;
+use std::vec::Vec;
+
+/// Magic bytes at the start of every graph cache file.
+const CACHE_MAGIC: &[u8] = b"EX13GRPH";
+
+/// Version of the cache format, increased on every incompatible change.
+const CACHE_VERSION: u32 = 1;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Size and modification time of the source file the cache was created from.
+#[derive(PartialEq, Debug)]
+struct SourceInfo {
+	length: u64,
+	modified_secs: u64,
+	modified_nanos: u32
+}
+
+impl SourceInfo {
+	fn from_file(name: &str) -> Result<SourceInfo, Error> {
+		let metadata = try!(fs::metadata(name));
+		let modified = try!(metadata.modified());
+		let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+		Ok(SourceInfo {
+			length: metadata.len(),
+			modified_secs: since_epoch.as_secs(),
+			modified_nanos: since_epoch.subsec_nanos()
+		})
+	}
+}
+
+/// Writer computing the FNV-1a checksum of all written bytes.
+struct ChecksumWriter<W: Write> {
+	inner: W,
+	checksum: u64
+}
+
+impl<W: Write> ChecksumWriter<W> {
+	fn write_u32(&mut self, value: u32) -> IOResult<()> {
+		let bytes = [value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8];
+		self.write_all(&bytes)
+	}
+
+	fn write_u64(&mut self, value: u64) -> IOResult<()> {
+		try!(self.write_u32(value as u32));
+		self.write_u32((value >> 32) as u32)
+	}
+
+	fn write_f64(&mut self, value: f64) -> IOResult<()> {
+		self.write_u64(value.to_bits())
+	}
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+		let written = try!(self.inner.write(buf));
+		self.checksum = checksum(self.checksum, &buf[..written]);
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> IOResult<()> {
+		self.inner.flush()
+	}
+}
+
+/// Continue the FNV-1a checksum with the given bytes.
+fn checksum(mut hash: u64, bytes: &[u8]) -> u64 {
+	for &byte in bytes.iter() {
+		hash ^= byte as u64;
+		hash = hash.wrapping_mul(FNV_PRIME);
+	}
+
+	return hash;
+}
+
+/// Little endian decoder over the bytes of a cache file.
+struct Decoder<'a> {
+	data: &'a [u8],
+	position: usize
+}
+
+impl<'a> Decoder<'a> {
+	fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+		if self.data.len() - self.position < length {
+			return Err(Error::from("Invalid graph cache! (Unexpected end of file)"));
+		}
+
+		let bytes = &self.data[self.position .. self.position + length];
+		self.position += length;
+		Ok(bytes)
+	}
+
+	fn read_u32(&mut self) -> Result<u32, Error> {
+		let bytes = try!(self.read_bytes(4));
+		Ok(bytes.iter().rev().fold(0, |value, &byte| (value << 8) | byte as u32))
+	}
+
+	fn read_u64(&mut self) -> Result<u64, Error> {
+		let low = try!(self.read_u32()) as u64;
+		let high = try!(self.read_u32()) as u64;
+		Ok((high << 32) | low)
+	}
+
+	fn read_usize(&mut self) -> Result<usize, Error> {
+		Ok(try!(self.read_u64()) as usize)
+	}
+
+	fn read_f64(&mut self) -> Result<f64, Error> {
+		Ok(f64::from_bits(try!(self.read_u64())))
+	}
+
+	/// Read the given number of values with the given decoding function.
+	fn read_array<T, F>(&mut self, count: usize, read: F) -> Result<Vec<T>, Error>
+			where F: Fn(&mut Decoder<'a>) -> Result<T, Error> {
+		// Every value takes at least four bytes, don't trust the count blindly
+		if (self.data.len() - self.position) / 4 < count {
+			return Err(Error::from("Invalid graph cache! (Unexpected end of file)"));
+		}
+
+		let mut values = Vec::with_capacity(count);
+		for _ in 0..count {
+			values.push(try!(read(self)));
+		}
+
+		Ok(values)
+	}
+}
+
+impl Graph {
+
+	/// Read the graph from the given file, using a binary cache next to it.
+	///
+	/// The cache is named like the file with an additional ".cache"
+	/// extension, preceded by the mode unless the graph is directed. The
+	/// graph is read from the file and the cache is (re)written if the cache
+	/// is missing, broken or made from a different version of the file,
+	/// reporting the progress of reading the file.
+	pub fn read_graph_from_file_cached<P: FnMut(&Progress)>(name: &str, mode: LoadMode,
+			progress: P) -> Result<Graph, Error> {
+		let cache_name = match mode {
+			LoadMode::Directed => format!("{}.cache", name),
+			LoadMode::Undirected => format!("{}.undirected.cache", name),
+			LoadMode::Reverse => format!("{}.reverse.cache", name)
+		};
+
+		match Graph::read_cache(&cache_name, name) {
+			Ok(graph) => { return Ok(graph); },
+			Err(_) => { }
+		}
+
+		let graph = try!(Graph::read_graph_from_file_with_progress(name, mode, progress));
+
+		// The graph is fine even if the cache can't be written
+		match graph.write_cache(&cache_name, name) {
+			Ok(_) => { },
+			Err(_) => { }
+		}
+
+		Ok(graph)
+	}
+
+	/// Write the graph read from the given source file to a binary cache.
+	pub fn write_cache(&self, name: &str, source_name: &str) -> Result<(), Error> {
+		let source = try!(SourceInfo::from_file(source_name));
+		let file = try!(File::create(name));
+
+		let mut buf = ChecksumWriter {
+			inner: BufWriter::new(file),
+			checksum: FNV_OFFSET_BASIS
+		};
+
+		try!(buf.write_all(CACHE_MAGIC));
+		try!(buf.write_u32(CACHE_VERSION));
+		try!(buf.write_u64(source.length));
+		try!(buf.write_u64(source.modified_secs));
+		try!(buf.write_u32(source.modified_nanos));
+		try!(buf.write_u64(self.num_nodes() as u64));
+		try!(buf.write_u64(self.num_arcs() as u64));
+
+		for node in self.nodes.iter() {
+			try!(buf.write_u64(node.id as u64));
+		}
+		for node in self.nodes.iter() {
+			try!(buf.write_f64(node.latitude));
+		}
+		for node in self.nodes.iter() {
+			try!(buf.write_f64(node.longitude));
+		}
+
+		for &offset in self.arc_offsets.iter() {
+			try!(buf.write_u64(offset as u64));
+		}
+		for &head_node in self.arc_heads.iter() {
+			try!(buf.write_u64(head_node as u64));
+		}
+		for &distance in self.arc_distances.iter() {
+			try!(buf.write_u64(distance));
+		}
+		for &max_speed in self.arc_max_speeds.iter() {
+			try!(buf.write_u64(max_speed));
+		}
+
+		let checksum = buf.checksum;
+		try!(buf.write_u64(checksum));
+		try!(buf.flush());
+
+		Ok(())
+	}
+
+	/// Read the graph from a binary cache of the given source file.
+	///
+	/// Fails if the cache is broken or doesn't belong to the current
+	/// version of the source file.
+	pub fn read_cache(name: &str, source_name: &str) -> Result<Graph, Error> {
+		let source = try!(SourceInfo::from_file(source_name));
+
+		let mut data = Vec::new();
+		try!(try!(File::open(name)).read_to_end(&mut data));
+
+		if data.len() < CACHE_MAGIC.len() + 8 || !data.starts_with(CACHE_MAGIC) {
+			return Err(Error::from("Invalid graph cache! (Unknown format)"));
+		}
+
+		let (content, stored_checksum) = data.split_at(data.len() - 8);
+		let mut decoder = Decoder { data: stored_checksum, position: 0 };
+		if try!(decoder.read_u64()) != checksum(FNV_OFFSET_BASIS, content) {
+			return Err(Error::from("Invalid graph cache! (Checksum mismatch)"));
+		}
+
+		let mut decoder = Decoder { data: content, position: CACHE_MAGIC.len() };
+		if try!(decoder.read_u32()) != CACHE_VERSION {
+			return Err(Error::from("Invalid graph cache! (Unsupported version)"));
+		}
+
+		let cached_source = SourceInfo {
+			length: try!(decoder.read_u64()),
+			modified_secs: try!(decoder.read_u64()),
+			modified_nanos: try!(decoder.read_u32())
+		};
+		if cached_source != source {
+			return Err(Error::from("Graph cache is stale!"));
+		}
+
+		let node_count = try!(decoder.read_usize());
+		let arc_count = try!(decoder.read_usize());
+
+		let ids = try!(decoder.read_array(node_count, Decoder::read_usize));
+		let latitudes = try!(decoder.read_array(node_count, Decoder::read_f64));
+		let longitudes = try!(decoder.read_array(node_count, Decoder::read_f64));
+
+		let nodes: Vec<Node> = ids.into_iter().zip(latitudes.into_iter().zip(longitudes.into_iter()))
+			.map(|(id, (latitude, longitude))| Node {
+				id: id,
+				latitude: latitude,
+				longitude: longitude
+			})
+			.collect();
+
+		let mut graph = Graph::new();
+		graph.nodes = Box::new(nodes);
+		graph.arc_offsets = try!(decoder.read_array(node_count + 1, Decoder::read_usize));
+		graph.arc_heads = try!(decoder.read_array(arc_count, Decoder::read_usize));
+		graph.arc_distances = try!(decoder.read_array(arc_count, Decoder::read_u64));
+		graph.arc_max_speeds = try!(decoder.read_array(arc_count, Decoder::read_u64));
+		graph.arc_costs = graph.arc_distances.iter().map(|&distance| distance as f64).collect();
+
+		if decoder.position != content.len() {
+			return Err(Error::from("Invalid graph cache! (Additional data)"));
+		}
+
+		let offsets_valid = graph.arc_offsets[0] == 0 && graph.arc_offsets[node_count] == arc_count
+			&& graph.arc_offsets.windows(2).all(|offsets| offsets[0] <= offsets[1]);
+		if !offsets_valid || graph.arc_heads.iter().any(|&head_node| head_node >= node_count) {
+			return Err(Error::from("Invalid graph cache! (Invalid arcs)"));
+		}
+
+		graph.build_reverse_arcs();
+		Ok(graph)
+	}
+}
+
+#[cfg(test)]
+fn copy_to_temp_dir(name: &str, file_name: &str) -> String {
+	let target = ::std::env::temp_dir().join(file_name);
+	fs::copy(name, &target).unwrap();
+	target.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn test_cache_read_write() {
+	let name = copy_to_temp_dir("graphs/test2.zip", "ex13_test_cache_read_write.zip");
+	let cache_name = format!("{}.cache", name);
+
+	let graph = Graph::read_graph_from_file(&name).unwrap();
+	graph.write_cache(&cache_name, &name).unwrap();
+
+	let cached = Graph::read_cache(&cache_name, &name).unwrap();
+	assert_eq!(format!("{}", graph), format!("{}", cached));
+	assert_eq!(graph.arc_offsets, cached.arc_offsets);
+	assert_eq!(graph.reverse_arc_ids, cached.reverse_arc_ids);
+	assert_eq!(graph.nodes.iter().map(|node| (node.id, node.latitude, node.longitude)).collect::<Vec<_>>(),
+		cached.nodes.iter().map(|node| (node.id, node.latitude, node.longitude)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_cache_invalid() {
+	let name = copy_to_temp_dir("graphs/test.zip", "ex13_test_cache_invalid.zip");
+	let cache_name = format!("{}.cache", name);
+
+	let graph = Graph::read_graph_from_file(&name).unwrap();
+	graph.write_cache(&cache_name, &name).unwrap();
+
+	// Flip a bit of the first latitude
+	let mut data = Vec::new();
+	File::open(&cache_name).unwrap().read_to_end(&mut data).unwrap();
+	data[50] ^= 1;
+	File::create(&cache_name).unwrap().write_all(&data).unwrap();
+	assert!(Graph::read_cache(&cache_name, &name).is_err());
+
+	// Cache of another source file
+	let other_name = copy_to_temp_dir("graphs/test2.zip", "ex13_test_cache_invalid_other.zip");
+	graph.write_cache(&cache_name, &other_name).unwrap();
+	assert!(Graph::read_cache(&cache_name, &name).is_err());
+
+	// Fallback to the source file replaces the cache
+	let cached = Graph::read_graph_from_file_cached(&name, LoadMode::Directed, |_| { }).unwrap();
+	assert_eq!(format!("{}", graph), format!("{}", cached));
+	assert!(Graph::read_cache(&cache_name, &name).is_ok());
+
+	// Other modes use their own cache
+	let reversed = Graph::read_graph_from_file_cached(&name, LoadMode::Reverse, |_| { }).unwrap();
+	assert_eq!(graph.num_arcs(), reversed.num_arcs());
+	assert!(format!("{}", graph) != format!("{}", reversed));
+	assert!(Graph::read_cache(&format!("{}.reverse.cache", name), &name).is_ok());
+	assert_eq!(format!("{}", cached), format!("{}", Graph::read_graph_from_file_cached(&name, LoadMode::Directed, |_| { }).unwrap()));
+}
\ No newline at end of file
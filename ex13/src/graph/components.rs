@@ -0,0 +1,194 @@
+use super::Graph;
+use super::subgraph::NodeMapping;
+
+use std::vec::Vec;
+
+/// Strongly connected components of a graph.
+pub struct Components {
+	component_ids: Vec<usize>,
+	sizes: Vec<usize>
+}
+
+impl Components {
+
+	/// Returns the component id of the node.
+	pub fn component(&self, node: usize) -> usize {
+		self.component_ids[node]
+	}
+
+	/// Returns the component ids of all nodes.
+	pub fn component_ids(&self) -> &Vec<usize> {
+		&self.component_ids
+	}
+
+	/// Returns the number of nodes in each component.
+	pub fn sizes(&self) -> &Vec<usize> {
+		&self.sizes
+	}
+
+	/// Returns the number of components.
+	pub fn num_components(&self) -> usize {
+		self.sizes.len()
+	}
+
+	/// Returns the id of the largest component.
+	///
+	/// The first of the largest components is returned if there are multiple.
+	pub fn largest(&self) -> Option<usize> {
+		let mut largest: Option<usize> = None;
+
+		for (component, &size) in self.sizes.iter().enumerate() {
+			match largest {
+				Some(largest) if self.sizes[largest] >= size => { },
+				_ => { largest = Some(component); }
+			}
+		}
+
+		return largest;
+	}
+
+	/// Returns the nodes of the component in ascending order.
+	pub fn nodes(&self, component: usize) -> Box<Vec<usize>> {
+		let nodes = (0..self.component_ids.len())
+			.filter(|&node| self.component_ids[node] == component)
+			.collect();
+
+		return Box::new(nodes);
+	}
+}
+
+impl Graph {
+
+	/// Compute the strongly connected components with Tarjan's algorithm.
+	///
+	/// The components are numbered in reverse topological order,
+	/// arcs between components only lead to components with lower ids.
+	pub fn compute_strongly_connected_components(&self) -> Components {
+		let node_count = self.num_nodes();
+
+		let mut indices = vec![usize::MAX; node_count];
+		let mut lowlinks = vec![0; node_count];
+		let mut on_stack = vec![false; node_count];
+		let mut next_index = 0;
+
+		let mut component_ids = vec![0; node_count];
+		let mut sizes = Vec::new();
+
+		// Nodes of the current components and the nodes of the depth first
+		// search with their next outgoing arc, to avoid recursion
+		let mut component_stack = Vec::new();
+		let mut search_stack: Vec<(usize, usize)> = Vec::new();
+
+		for root in 0..node_count {
+			if indices[root] != usize::MAX {
+				continue;
+			}
+
+			indices[root] = next_index;
+			lowlinks[root] = next_index;
+			next_index += 1;
+			component_stack.push(root);
+			on_stack[root] = true;
+			search_stack.push((root, self.arc_offsets[root]));
+
+			while let Some(&mut (node, ref mut next_arc)) = search_stack.last_mut() {
+				if *next_arc < self.arc_offsets[node + 1] {
+					let head_node = self.arc_heads[*next_arc];
+					*next_arc += 1;
+
+					if indices[head_node] == usize::MAX {
+						indices[head_node] = next_index;
+						lowlinks[head_node] = next_index;
+						next_index += 1;
+						component_stack.push(head_node);
+						on_stack[head_node] = true;
+						search_stack.push((head_node, self.arc_offsets[head_node]));
+					} else if on_stack[head_node] && indices[head_node] < lowlinks[node] {
+						lowlinks[node] = indices[head_node];
+					}
+
+					continue;
+				}
+
+				search_stack.pop();
+				if let Some(&(parent, _)) = search_stack.last() {
+					if lowlinks[node] < lowlinks[parent] {
+						lowlinks[parent] = lowlinks[node];
+					}
+				}
+
+				// The node is the root of a component, collect its nodes
+				if lowlinks[node] == indices[node] {
+					let component = sizes.len();
+					let mut size = 0;
+
+					loop {
+						let member = component_stack.pop().unwrap();
+						on_stack[member] = false;
+						component_ids[member] = component;
+						size += 1;
+
+						if member == node {
+							break;
+						}
+					}
+
+					sizes.push(size);
+				}
+			}
+		}
+
+		Components {
+			component_ids: component_ids,
+			sizes: sizes
+		}
+	}
+
+	/// Extract the largest strongly connected component as a new graph.
+	///
+	/// The nodes keep their order but are renumbered from zero,
+	/// the arc costs of the new graph are the distances.
+	pub fn extract_largest_scc(&self) -> (Graph, NodeMapping) {
+		let components = self.compute_strongly_connected_components();
+		let nodes = match components.largest() {
+			Some(component) => components.nodes(component),
+			None => Box::new(Vec::new())
+		};
+
+		self.extract_nodes(&nodes)
+	}
+}
+
+#[test]
+fn test_strongly_connected_components() {
+	let graph = Graph::read_graph_from_file("graphs/test2.zip").unwrap();
+	let components = graph.compute_strongly_connected_components();
+
+	assert_eq!(4, components.num_components());
+	assert_eq!(components.component(1), components.component(2));
+	assert_eq!(components.component(1), components.component(3));
+	assert_eq!(components.component(4), components.component(5));
+	assert!(components.component(0) != components.component(1));
+	assert!(components.component(4) != components.component(1));
+
+	// Arcs between components lead to lower component ids
+	assert!(components.component(0) > components.component(1));
+	assert!(components.component(5) > components.component(6));
+
+	let mut sizes = components.sizes().clone();
+	sizes.sort();
+	assert_eq!(vec![1, 1, 2, 3], sizes);
+	assert_eq!(vec![1, 2, 3], *components.nodes(components.largest().unwrap()));
+}
+
+#[test]
+fn test_extract_largest_scc() {
+	let graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+	let (lcc, mapping) = graph.extract_largest_scc();
+
+	assert_eq!(vec![1, 2, 3], *mapping.old_nodes());
+	assert_eq!("[0->1(20), 1->2(50), 2->0(40)]", format!("{}", lcc));
+	assert_eq!(vec![0, 1, 2], lcc.nodes.iter().map(|node| node.id).collect::<Vec<_>>());
+	assert_eq!(graph.nodes[2].latitude, lcc.nodes[1].latitude);
+	assert_eq!(1, lcc.compute_strongly_connected_components().num_components());
+}
\ No newline at end of file
@@ -0,0 +1,826 @@
+use super::{DistanceMatrix, Error, Graph, ShortestPaths};
+
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::result::Result;
+use std::vec::Vec;
+
+/// Maximum number of settled nodes of a single witness search.
+///
+/// Aborted witness searches only add superfluous shortcuts,
+/// they never break the correctness of the hierarchy.
+const WITNESS_SEARCH_LIMIT: usize = 500;
+
+enum ArcKind {
+	/// Id of the arc in the graph.
+	Original(usize),
+	/// Ids of the two hierarchy arcs bridged by the shortcut.
+	Shortcut(usize, usize)
+}
+
+struct HierarchyArc {
+	tail_node_id: usize,
+	head_node_id: usize,
+	costs: f64,
+	kind: ArcKind
+}
+
+/// A contraction hierarchy over the arc costs of a graph.
+///
+/// The hierarchy is only valid for the graph and the arc costs it was
+/// created with. Changing the metric requires a new hierarchy.
+pub struct ContractionHierarchy {
+	ranks: Box<Vec<usize>>,
+	arcs: Box<Vec<HierarchyArc>>,
+	/// Arcs to higher ranked nodes by tail node.
+	upward_lists: Box<Vec<Vec<usize>>>,
+	/// Arcs from higher ranked nodes by head node.
+	downward_lists: Box<Vec<Vec<usize>>>
+}
+
+/// Local Dijkstra search used to find witness paths during contraction.
+struct WitnessSearch {
+	distances: Vec<Option<f64>>,
+	settled: Vec<bool>,
+	touched_nodes: Vec<usize>
+}
+
+impl WitnessSearch {
+	fn new(node_count: usize) -> WitnessSearch {
+		WitnessSearch {
+			distances: vec![None; node_count],
+			settled: vec![false; node_count],
+			touched_nodes: Vec::new()
+		}
+	}
+
+	/// Compute distances from the start node among the uncontracted nodes
+	/// without passing the excluded node, up to the given max costs.
+	fn run(&mut self, start_node: usize, excluded_node: usize, max_costs: f64,
+			arcs: &Vec<HierarchyArc>, out_arcs: &Vec<Vec<usize>>, contracted: &Vec<bool>) {
+		for node in self.touched_nodes.drain(..) {
+			self.distances[node] = None;
+			self.settled[node] = false;
+		}
+
+		self.distances[start_node] = Some(0.0);
+		self.touched_nodes.push(start_node);
+
+		let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+		active_nodes.push((0, start_node));
+		let mut num_settled = 0;
+
+		while let Some((_, node)) = active_nodes.pop() {
+			if self.settled[node] {
+				continue;
+			}
+
+			self.settled[node] = true;
+			num_settled += 1;
+
+			let node_distance = self.distances[node].unwrap();
+			if node_distance > max_costs || num_settled > WITNESS_SEARCH_LIMIT {
+				return;
+			}
+
+			for &arc_id in out_arcs[node].iter() {
+				let arc = &arcs[arc_id];
+				if contracted[arc.head_node_id] || arc.head_node_id == excluded_node {
+					continue;
+				}
+
+				let next_distance = node_distance + arc.costs;
+				match self.distances[arc.head_node_id] {
+					Some(distance) if distance <= next_distance => { continue; },
+					None => { self.touched_nodes.push(arc.head_node_id); },
+					_ => { }
+				}
+
+				self.distances[arc.head_node_id] = Some(next_distance);
+
+				// Use negative numbers since we only have a max-heap
+				active_nodes.push((-(next_distance * 1000.0) as i64, arc.head_node_id));
+			}
+		}
+	}
+
+	fn distance(&self, node: usize) -> Option<f64> {
+		self.distances[node]
+	}
+}
+
+/// Return the cheapest arc of the list for every neighbor.
+///
+/// Arcs to contracted nodes and to the given node are ignored.
+fn cheapest_arcs(list: &Vec<usize>, arcs: &Vec<HierarchyArc>, contracted: &Vec<bool>,
+		node: usize, incoming: bool) -> Vec<usize> {
+	let mut result: Vec<usize> = Vec::new();
+
+	for &arc_id in list.iter() {
+		let arc = &arcs[arc_id];
+		let neighbor = if incoming { arc.tail_node_id } else { arc.head_node_id };
+		if contracted[neighbor] || neighbor == node {
+			continue;
+		}
+
+		let position = result.iter().position(|&other_id| {
+			let other = &arcs[other_id];
+			neighbor == if incoming { other.tail_node_id } else { other.head_node_id }
+		});
+
+		match position {
+			Some(index) => {
+				if arc.costs < arcs[result[index]].costs {
+					result[index] = arc_id;
+				}
+			},
+			None => { result.push(arc_id); }
+		}
+	}
+
+	return result;
+}
+
+/// Compute the shortcuts required to contract the given node.
+///
+/// The result is a list of the incoming and outgoing arc id of every shortcut.
+fn find_shortcuts(node: usize, arcs: &Vec<HierarchyArc>, in_arcs: &Vec<Vec<usize>>,
+		out_arcs: &Vec<Vec<usize>>, contracted: &Vec<bool>, witness: &mut WitnessSearch)
+		-> Vec<(usize, usize)> {
+	let incoming = cheapest_arcs(&in_arcs[node], arcs, contracted, node, true);
+	let outgoing = cheapest_arcs(&out_arcs[node], arcs, contracted, node, false);
+	let mut shortcuts = Vec::new();
+
+	for &in_id in incoming.iter() {
+		let tail_node = arcs[in_id].tail_node_id;
+
+		let mut max_costs = None;
+		for &out_id in outgoing.iter() {
+			if arcs[out_id].head_node_id != tail_node {
+				let costs = arcs[in_id].costs + arcs[out_id].costs;
+				max_costs = Some(max_costs.map_or(costs, |max_costs: f64| max_costs.max(costs)));
+			}
+		}
+
+		let max_costs = match max_costs {
+			Some(costs) => costs,
+			None => { continue; }
+		};
+
+		witness.run(tail_node, node, max_costs, arcs, out_arcs, contracted);
+
+		for &out_id in outgoing.iter() {
+			let head_node = arcs[out_id].head_node_id;
+			if head_node == tail_node {
+				continue;
+			}
+
+			let costs = arcs[in_id].costs + arcs[out_id].costs;
+			match witness.distance(head_node) {
+				Some(distance) if distance <= costs => { },
+				_ => { shortcuts.push((in_id, out_id)); }
+			}
+		}
+	}
+
+	return shortcuts;
+}
+
+impl ContractionHierarchy {
+
+	/// Contract all nodes of the graph by their importance.
+	///
+	/// Nodes are ordered by their edge difference (shortcuts added minus
+	/// arcs removed) plus the number of already contracted neighbors.
+	pub fn new(graph: &Graph) -> ContractionHierarchy {
+		let node_count = graph.num_nodes();
+
+		let mut arcs = Box::new(Vec::<HierarchyArc>::new());
+		let mut out_arcs = vec![Vec::<usize>::new(); node_count];
+		let mut in_arcs = vec![Vec::<usize>::new(); node_count];
+
+		for tail_node in 0..node_count {
+			for arc in graph.arcs(tail_node) {
+				// Loops are never part of a shortest path
+				let head_node = graph.arc_heads[arc];
+				if head_node == tail_node {
+					continue;
+				}
+
+				out_arcs[tail_node].push(arcs.len());
+				in_arcs[head_node].push(arcs.len());
+				arcs.push(HierarchyArc {
+					tail_node_id: tail_node,
+					head_node_id: head_node,
+					costs: graph.arc_costs[arc],
+					kind: ArcKind::Original(arc)
+				});
+			}
+		}
+
+		let mut contracted = vec![false; node_count];
+		let mut contracted_neighbors = vec![0_i64; node_count];
+		let mut ranks = Box::new(vec![0; node_count]);
+		let mut witness = WitnessSearch::new(node_count);
+
+		let mut queue = BinaryHeap::<(i64, usize)>::new();
+		for node in 0..node_count {
+			let shortcuts = find_shortcuts(node, &arcs, &in_arcs, &out_arcs, &contracted, &mut witness);
+			let priority = shortcuts.len() as i64 - (in_arcs[node].len() + out_arcs[node].len()) as i64;
+
+			// Use negative numbers since we only have a max-heap
+			queue.push((-priority, node));
+		}
+
+		let mut rank = 0;
+		while let Some((_, node)) = queue.pop() {
+			let shortcuts = find_shortcuts(node, &arcs, &in_arcs, &out_arcs, &contracted, &mut witness);
+
+			// Priorities are updated lazily, contract the node only if it is still the least important one
+			let priority = shortcuts.len() as i64 - (in_arcs[node].len() + out_arcs[node].len()) as i64
+				+ contracted_neighbors[node];
+			match queue.peek() {
+				Some(&(next_priority, _)) if -priority < next_priority => {
+					queue.push((-priority, node));
+					continue;
+				},
+				_ => { }
+			}
+
+			for (in_id, out_id) in shortcuts.into_iter() {
+				let (tail_node, head_node) = (arcs[in_id].tail_node_id, arcs[out_id].head_node_id);
+				let costs = arcs[in_id].costs + arcs[out_id].costs;
+
+				out_arcs[tail_node].push(arcs.len());
+				in_arcs[head_node].push(arcs.len());
+				arcs.push(HierarchyArc {
+					tail_node_id: tail_node,
+					head_node_id: head_node,
+					costs: costs,
+					kind: ArcKind::Shortcut(in_id, out_id)
+				});
+			}
+
+			contracted[node] = true;
+			ranks[node] = rank;
+			rank += 1;
+
+			// Drop the arcs to the contracted node from its neighbors
+			let mut neighbors: Vec<usize> = in_arcs[node].iter().map(|&id| arcs[id].tail_node_id)
+				.chain(out_arcs[node].iter().map(|&id| arcs[id].head_node_id))
+				.filter(|&neighbor| !contracted[neighbor])
+				.collect();
+			neighbors.sort();
+			neighbors.dedup();
+
+			for &neighbor in neighbors.iter() {
+				contracted_neighbors[neighbor] += 1;
+				out_arcs[neighbor].retain(|&id| !contracted[arcs[id].head_node_id]);
+				in_arcs[neighbor].retain(|&id| !contracted[arcs[id].tail_node_id]);
+			}
+		}
+
+		ContractionHierarchy::from_arcs(ranks, arcs)
+	}
+
+	/// Split the arcs into upward and downward lists by the node ranks.
+	fn from_arcs(ranks: Box<Vec<usize>>, arcs: Box<Vec<HierarchyArc>>) -> ContractionHierarchy {
+		let mut upward_lists = Box::new(vec![Vec::new(); ranks.len()]);
+		let mut downward_lists = Box::new(vec![Vec::new(); ranks.len()]);
+
+		for (arc_id, arc) in arcs.iter().enumerate() {
+			if ranks[arc.tail_node_id] < ranks[arc.head_node_id] {
+				upward_lists[arc.tail_node_id].push(arc_id);
+			} else {
+				downward_lists[arc.head_node_id].push(arc_id);
+			}
+		}
+
+		ContractionHierarchy {
+			ranks: ranks,
+			arcs: arcs,
+			upward_lists: upward_lists,
+			downward_lists: downward_lists
+		}
+	}
+
+	/// Returns the number of shortcuts added by the contraction.
+	pub fn num_shortcuts(&self) -> usize {
+		self.arcs.iter().filter(|arc| match arc.kind {
+			ArcKind::Shortcut(..) => true,
+			ArcKind::Original(..) => false
+		}).count()
+	}
+
+	/// Compute the shortest path between two nodes.
+	///
+	/// Runs an upward Dijkstra search from both nodes. The result is a tuple
+	/// of the distance, the travel time with the given max speed, the nodes
+	/// on the path and the number of settled nodes.
+	pub fn compute_shortest_path(&self, graph: &Graph, start_node: usize, end_node: usize,
+			max_speed: u64) -> Option<(f64, f64, Box<Vec<usize>>, usize)> {
+		let (path_arcs, num_settled) = match self.compute_path_arcs(start_node, end_node) {
+			Some(result) => result,
+			None => { return None; }
+		};
+
+		let mut path = Box::new(vec![start_node]);
+		let mut distance = 0.0;
+		let mut time = 0.0;
+
+		for &(_, arc) in path_arcs.iter() {
+			distance += graph.arc_distances[arc] as f64;
+			time += graph.arc_travel_time(arc, max_speed);
+			path.push(graph.arc_heads[arc]);
+		}
+
+		return Some((distance, time, path, num_settled));
+	}
+
+	/// Compute the shortest path between two nodes as search result.
+	///
+	/// Only the nodes on the path are settled, but travel_to and
+	/// generate_mapbb can follow the path to the end node.
+	pub fn compute_shortest_path_tree(&self, graph: &Graph, start_node: usize,
+			end_node: usize) -> Option<ShortestPaths> {
+		let (path_arcs, num_settled) = match self.compute_path_arcs(start_node, end_node) {
+			Some(result) => result,
+			None => { return None; }
+		};
+
+		let mut paths = ShortestPaths::new(graph.num_nodes(), start_node);
+		paths.settled[start_node] = true;
+		paths.num_settled = num_settled;
+
+		let mut distance = 0.0;
+		for &(tail_node, arc) in path_arcs.iter() {
+			let head_node = graph.arc_heads[arc];
+			distance += graph.arc_costs[arc];
+
+			paths.distances[head_node] = Some(distance);
+			paths.settled[head_node] = true;
+			paths.traceback_arcs[head_node] = Some((tail_node, arc));
+		}
+
+		return Some(paths);
+	}
+
+	/// Compute the distances and travel times between all sources and targets.
+	///
+	/// Runs a backward upward search from every target, which stores its costs
+	/// in buckets at the settled nodes. The forward upward search from every
+	/// source then combines its costs with the buckets of its settled nodes.
+	pub fn compute_distance_matrix(&self, graph: &Graph, sources: &Vec<usize>, targets: &Vec<usize>,
+			max_speed: u64) -> DistanceMatrix {
+		let node_count = self.ranks.len();
+
+		// Distance and travel time of the hierarchy arcs, shortcuts only bridge arcs created before them
+		let mut arc_lengths: Vec<(f64, f64)> = Vec::with_capacity(self.arcs.len());
+		for arc in self.arcs.iter() {
+			let length = match arc.kind {
+				ArcKind::Original(graph_arc) => {
+					(graph.arc_distances[graph_arc] as f64, graph.arc_travel_time(graph_arc, max_speed))
+				},
+				ArcKind::Shortcut(first, second) => {
+					(arc_lengths[first].0 + arc_lengths[second].0, arc_lengths[first].1 + arc_lengths[second].1)
+				}
+			};
+			arc_lengths.push(length);
+		}
+
+		let mut matrix = DistanceMatrix::new(sources, targets);
+		let mut labels = vec![None; node_count];
+		let mut settled_nodes = Vec::new();
+
+		// Target index, costs, distance and time of the backward searches by node
+		let mut buckets: Vec<Vec<(usize, f64, f64, f64)>> = vec![Vec::new(); node_count];
+		for (target_index, &target) in targets.iter().enumerate() {
+			self.search_upward(target, false, &arc_lengths, &mut labels, &mut settled_nodes);
+			for &(node, costs, distance, time) in settled_nodes.iter() {
+				buckets[node].push((target_index, costs, distance, time));
+			}
+		}
+
+		for (source_index, &source) in sources.iter().enumerate() {
+			self.search_upward(source, true, &arc_lengths, &mut labels, &mut settled_nodes);
+			for &(node, costs, distance, time) in settled_nodes.iter() {
+				for &(target_index, target_costs, target_distance, target_time) in buckets[node].iter() {
+					matrix.update(source_index, target_index, costs + target_costs,
+						distance + target_distance, time + target_time);
+				}
+			}
+		}
+
+		return matrix;
+	}
+
+	/// Run a Dijkstra search from the start node over the arcs to higher ranked nodes.
+	///
+	/// The backward search follows the arcs from higher ranked nodes in reverse.
+	/// The settled nodes are stored with their costs, distance and travel time.
+	/// The labels are used for the search and are reset afterwards.
+	fn search_upward(&self, start_node: usize, forward: bool, arc_lengths: &Vec<(f64, f64)>,
+			labels: &mut Vec<Option<(f64, f64, f64)>>, settled_nodes: &mut Vec<(usize, f64, f64, f64)>) {
+		let mut touched_nodes = vec![start_node];
+		let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+		labels[start_node] = Some((0.0, 0.0, 0.0));
+		active_nodes.push((0, start_node));
+		settled_nodes.clear();
+
+		let lists = if forward { &self.upward_lists } else { &self.downward_lists };
+
+		while let Some((key, node)) = active_nodes.pop() {
+			// The node was reached cheaper after it was added
+			let (costs, distance, time) = labels[node].unwrap();
+			if key != -(costs * 1000.0) as i64 {
+				continue;
+			}
+
+			settled_nodes.push((node, costs, distance, time));
+
+			for &arc_id in lists[node].iter() {
+				let arc = &self.arcs[arc_id];
+				let next_node = if forward { arc.head_node_id } else { arc.tail_node_id };
+				let next_costs = costs + arc.costs;
+
+				match labels[next_node] {
+					Some((other_costs, _, _)) if other_costs <= next_costs => { continue; },
+					None => { touched_nodes.push(next_node); },
+					_ => { }
+				}
+
+				labels[next_node] = Some((next_costs, distance + arc_lengths[arc_id].0, time + arc_lengths[arc_id].1));
+
+				// Use negative numbers since we only have a max-heap
+				active_nodes.push((-(next_costs * 1000.0) as i64, next_node));
+			}
+		}
+
+		for node in touched_nodes.into_iter() {
+			labels[node] = None;
+		}
+	}
+
+	/// Search the hierarchy and unpack the shortest path.
+	///
+	/// The result is a tuple of the original arcs on the path as tail node
+	/// and arc id and the number of settled nodes.
+	fn compute_path_arcs(&self, start_node: usize, end_node: usize) -> Option<(Vec<(usize, usize)>, usize)> {
+		let node_count = self.ranks.len();
+
+		// Index 0 holds the forward search, index 1 the backward search
+		let mut distances = [vec![None; node_count], vec![None; node_count]];
+		let mut settled = [vec![false; node_count], vec![false; node_count]];
+		let mut traceback_arcs = [vec![None; node_count], vec![None; node_count]];
+		let mut active_nodes = [BinaryHeap::<(i64, usize)>::new(), BinaryHeap::<(i64, usize)>::new()];
+		let mut num_settled = 0;
+
+		distances[0][start_node] = Some(0.0);
+		distances[1][end_node] = Some(0.0);
+		active_nodes[0].push((0, start_node));
+		active_nodes[1].push((0, end_node));
+
+		// Costs and meeting node of the best path found so far
+		let mut best: Option<(f64, usize)> = None;
+
+		loop {
+			let direction = match (active_nodes[0].peek(), active_nodes[1].peek()) {
+				(None, None) => { break; },
+				(Some(_), None) => 0,
+				(None, Some(_)) => 1,
+				(Some(forward), Some(backward)) => if forward.0 >= backward.0 { 0 } else { 1 }
+			};
+
+			let (_, node) = active_nodes[direction].pop().unwrap();
+			if settled[direction][node] {
+				continue;
+			}
+
+			settled[direction][node] = true;
+			num_settled += 1;
+
+			let node_distance: f64 = distances[direction][node].unwrap();
+
+			// The search in this direction can not improve the best path anymore
+			match best {
+				Some((costs, _)) if node_distance >= costs => {
+					active_nodes[direction].clear();
+					continue;
+				},
+				_ => { }
+			}
+
+			match distances[1 - direction][node] {
+				Some(distance) => {
+					match best {
+						Some((costs, _)) if costs <= node_distance + distance => { },
+						_ => { best = Some((node_distance + distance, node)); }
+					}
+				},
+				None => { }
+			}
+
+			let lists = if direction == 0 { &self.upward_lists } else { &self.downward_lists };
+			for &arc_id in lists[node].iter() {
+				let arc = &self.arcs[arc_id];
+				let next_node = if direction == 0 { arc.head_node_id } else { arc.tail_node_id };
+				if settled[direction][next_node] {
+					continue;
+				}
+
+				let next_distance = node_distance + arc.costs;
+				match distances[direction][next_node] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+
+				distances[direction][next_node] = Some(next_distance);
+				traceback_arcs[direction][next_node] = Some(arc_id);
+
+				// Use negative numbers since we only have a max-heap
+				active_nodes[direction].push((-(next_distance * 1000.0) as i64, next_node));
+			}
+		}
+
+		let meeting_node = match best {
+			Some((_, node)) => node,
+			None => { return None; }
+		};
+
+		// Collect the hierarchy arcs from the start node over the meeting node to the end node
+		let mut hierarchy_arcs = Vec::new();
+
+		let mut node = meeting_node;
+		while let Some(arc_id) = traceback_arcs[0][node] {
+			hierarchy_arcs.push(arc_id);
+			node = self.arcs[arc_id].tail_node_id;
+		}
+		hierarchy_arcs.reverse();
+
+		let mut node = meeting_node;
+		while let Some(arc_id) = traceback_arcs[1][node] {
+			hierarchy_arcs.push(arc_id);
+			node = self.arcs[arc_id].head_node_id;
+		}
+
+		// Unpack the shortcuts into the original arcs
+		let mut path_arcs = Vec::new();
+		for &arc_id in hierarchy_arcs.iter() {
+			let mut pending_arcs = vec![arc_id];
+
+			while let Some(arc_id) = pending_arcs.pop() {
+				let arc = &self.arcs[arc_id];
+				match arc.kind {
+					ArcKind::Original(graph_arc) => {
+						path_arcs.push((arc.tail_node_id, graph_arc));
+					},
+					ArcKind::Shortcut(first, second) => {
+						pending_arcs.push(second);
+						pending_arcs.push(first);
+					}
+				}
+			}
+		}
+
+		return Some((path_arcs, num_settled));
+	}
+
+	/// Write the hierarchy to the file with the given name.
+	pub fn write_to_file(&self, name: &str) -> Result<(), Error> {
+		let file = try!(File::create(name));
+		let mut buf = BufWriter::new(file);
+
+		try!(writeln!(buf, "# Number of nodes."));
+		try!(writeln!(buf, "{}", self.ranks.len()));
+		try!(writeln!(buf, "# Number of arcs."));
+		try!(writeln!(buf, "{}", self.arcs.len()));
+		try!(writeln!(buf, "# Node ranks."));
+		for rank in self.ranks.iter() {
+			try!(writeln!(buf, "{}", rank));
+		}
+
+		try!(writeln!(buf, "# Arc information."));
+		for arc in self.arcs.iter() {
+			match arc.kind {
+				ArcKind::Original(graph_arc) => {
+					try!(writeln!(buf, "{} {} {} o {}", arc.tail_node_id, arc.head_node_id, arc.costs, graph_arc));
+				},
+				ArcKind::Shortcut(first, second) => {
+					try!(writeln!(buf, "{} {} {} s {} {}", arc.tail_node_id, arc.head_node_id, arc.costs,
+						first, second));
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Read a hierarchy for the given graph from the file with the given name.
+	///
+	/// Fails if the hierarchy was created for another graph.
+	pub fn read_from_file(graph: &Graph, name: &str) -> Result<ContractionHierarchy, Error> {
+		let file = try!(File::open(name));
+		let buf = BufReader::new(file);
+
+		let mut line_number = 0;
+		let mut total_line_number = 0;
+
+		let mut node_count = 0;
+		let mut arc_count = 0;
+
+		let mut ranks = Box::new(Vec::new());
+		let mut arcs = Box::new(Vec::new());
+
+		for line_res in buf.lines() {
+			let line = try!(line_res);
+			let line = line.trim();
+
+			total_line_number += 1;
+
+			if line.starts_with("#") || line.is_empty() {
+				continue;
+			}
+
+			line_number += 1;
+
+			let parts: Vec<&str> = line.split(' ').collect();
+
+			match line_number {
+				1 => {
+					node_count = try!(parts[0].parse::<usize>());
+					if node_count != graph.num_nodes() {
+						return Err(Error::from(format!("Hierarchy does not match graph! (node count, line {})",
+							total_line_number)));
+					}
+
+					ranks.reserve_exact(node_count);
+				},
+				2 => {
+					arc_count = try!(parts[0].parse::<usize>());
+					arcs.reserve_exact(arc_count);
+				},
+				_ => {
+					if line_number < node_count + 3 {
+						let rank = try!(parts[0].parse::<usize>());
+						if rank >= node_count {
+							return Err(Error::from(format!("Invalid hierarchy file! (Invalid rank, line {})",
+								total_line_number)));
+						}
+
+						ranks.push(rank);
+					} else if line_number < node_count + arc_count + 3 {
+						if parts.len() < 5 {
+							return Err(Error::from(format!("Invalid hierarchy file! (Invalid arc, line {})",
+								total_line_number)));
+						}
+
+						let tail_node = try!(parts[0].parse::<usize>());
+						let head_node = try!(parts[1].parse::<usize>());
+						let costs = try!(parts[2].parse::<f64>());
+
+						if tail_node >= node_count || head_node >= node_count {
+							return Err(Error::from(format!("Invalid hierarchy file! (Invalid node, line {})",
+								total_line_number)));
+						}
+
+						let kind = match (parts[3], parts.len()) {
+							("o", 5) => {
+								let arc = try!(parts[4].parse::<usize>());
+								let valid = graph.arcs(tail_node).any(|index| index == arc)
+									&& graph.arc_heads[arc] == head_node && graph.arc_costs[arc] == costs;
+								if !valid {
+									return Err(Error::from(format!("Hierarchy does not match graph! (arc, line {})",
+										total_line_number)));
+								}
+
+								ArcKind::Original(arc)
+							},
+							("s", 6) => {
+								let first = try!(parts[4].parse::<usize>());
+								let second = try!(parts[5].parse::<usize>());
+
+								// Shortcuts only bridge arcs created before them
+								if first >= arcs.len() || second >= arcs.len() {
+									return Err(Error::from(format!("Invalid hierarchy file! (Invalid shortcut, line {})",
+										total_line_number)));
+								}
+
+								ArcKind::Shortcut(first, second)
+							},
+							_ => {
+								return Err(Error::from(format!("Invalid hierarchy file! (Invalid arc, line {})",
+									total_line_number)));
+							}
+						};
+
+						arcs.push(HierarchyArc {
+							tail_node_id: tail_node,
+							head_node_id: head_node,
+							costs: costs,
+							kind: kind
+						});
+					} else {
+						return Err(Error::from(format!("Invalid hierarchy file! (Additional lines, line {})",
+							total_line_number)));
+					}
+				}
+			}
+		}
+
+		if ranks.len() != node_count || arcs.len() != arc_count {
+			return Err(Error::from("Invalid hierarchy file! (Missing lines)"));
+		}
+
+		Ok(ContractionHierarchy::from_arcs(ranks, arcs))
+	}
+}
+
+#[test]
+fn test_hierarchy_shortest() {
+	for name in ["graphs/test.zip", "graphs/test2.zip", "graphs/test3.zip"].iter() {
+		let graph = Graph::read_graph_from_file(name).unwrap();
+		let hierarchy = ContractionHierarchy::new(&graph);
+
+		for start in 0..graph.num_nodes() {
+			for end in 0..graph.num_nodes() {
+				let expected = graph.compute_shortest_path_dijkstra(start, end, 130);
+				let path = hierarchy.compute_shortest_path(&graph, start, end, 130);
+
+				assert_eq!(expected.map(|path| path.0), path.map(|path| path.0));
+			}
+		}
+	}
+}
+
+#[test]
+fn test_hierarchy_travel_to() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	graph.set_arc_costs_to_travel_time(130);
+
+	let hierarchy = ContractionHierarchy::new(&graph);
+	let (_, _, path, _) = hierarchy.compute_shortest_path(&graph, 0, 5, 130).unwrap();
+	assert_eq!(vec![0, 3, 5], *path);
+
+	let paths = hierarchy.compute_shortest_path_tree(&graph, 0, 5).unwrap();
+	assert_eq!(3700.0, graph.travel_to(&paths, 5, 130).0);
+
+	let mut mapbb = String::new();
+	graph.generate_mapbb(&paths, &mut mapbb, 5);
+	assert!(mapbb.starts_with("48.0300,7.8100"));
+	assert!(mapbb.ends_with("48.0000,7.8000"));
+
+	assert!(hierarchy.compute_shortest_path_tree(&graph, 5, 6).is_none());
+}
+
+#[test]
+fn test_hierarchy_file() {
+	let graph = Graph::read_graph_from_file("graphs/test2.zip").unwrap();
+	let hierarchy = ContractionHierarchy::new(&graph);
+
+	let name = ::std::env::temp_dir().join("ex13_test_hierarchy_file.ch");
+	let name = name.to_str().unwrap();
+	hierarchy.write_to_file(name).unwrap();
+
+	let loaded = ContractionHierarchy::read_from_file(&graph, name).unwrap();
+	assert_eq!(hierarchy.ranks, loaded.ranks);
+	assert_eq!(hierarchy.num_shortcuts(), loaded.num_shortcuts());
+	for start in 0..graph.num_nodes() {
+		for end in 0..graph.num_nodes() {
+			assert_eq!(hierarchy.compute_path_arcs(start, end).map(|path| path.0),
+				loaded.compute_path_arcs(start, end).map(|path| path.0));
+		}
+	}
+
+	let other_graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+	assert!(ContractionHierarchy::read_from_file(&other_graph, name).is_err());
+}
+
+#[test]
+fn test_hierarchy_distance_matrix() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let nodes: Vec<usize> = (0..graph.num_nodes()).collect();
+
+	for &metric in [false, true].iter() {
+		if metric {
+			graph.set_arc_costs_to_travel_time(130);
+		}
+
+		let hierarchy = ContractionHierarchy::new(&graph);
+		let matrix = hierarchy.compute_distance_matrix(&graph, &nodes, &nodes, 130);
+		let expected = graph.compute_distance_matrix(&nodes, &nodes, 130, 1);
+
+		// Paths with the same costs may differ in the other criterion
+		for source in 0..nodes.len() {
+			for target in 0..nodes.len() {
+				match metric {
+					false => { assert_eq!(expected.distance(source, target), matrix.distance(source, target)); },
+					true => {
+						assert_eq!(expected.time(source, target).map(|time| (time * 1000.0).round()),
+							matrix.time(source, target).map(|time| (time * 1000.0).round()));
+					}
+				}
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,436 @@
+use super::{Error, Graph, ShortestPaths, EARTH_RADIUS};
+
+use std::fs::File;
+use std::io::{BufWriter, Result as IOResult, Write};
+use std::result::Result;
+use std::vec::Vec;
+
+/// Namespace of the elements in the extensions of GPX files, which
+/// must not be in the GPX namespace.
+pub(super) const GPX_EXTENSIONS_NAMESPACE: &str = "urn:ex13:gpx";
+
+/// File format of an exported route.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RouteFormat {
+	GeoJson,
+	Gpx,
+	Kml,
+	/// Forum style [map] tag, like generate_mapbb.
+	Mapbb
+}
+
+impl RouteFormat {
+
+	/// Returns the format for the extension of the file name.
+	pub fn from_file_name(name: &str) -> Option<RouteFormat> {
+		let name = name.to_lowercase();
+
+		if name.ends_with(".geojson") || name.ends_with(".json") {
+			Some(RouteFormat::GeoJson)
+		} else if name.ends_with(".gpx") {
+			Some(RouteFormat::Gpx)
+		} else if name.ends_with(".kml") {
+			Some(RouteFormat::Kml)
+		} else if name.ends_with(".map") {
+			Some(RouteFormat::Mapbb)
+		} else {
+			None
+		}
+	}
+
+	/// Returns the given format or else the format for the extension of the file name.
+	pub(super) fn for_file(name: &str, format: Option<RouteFormat>) -> Result<RouteFormat, Error> {
+		match format.or_else(|| RouteFormat::from_file_name(name)) {
+			Some(format) => Ok(format),
+			None => Err(Error::from(format!("Unknown route format of file {}!", name)))
+		}
+	}
+}
+
+/// Consecutive arcs of a route with the same max speed.
+pub struct RouteSegment {
+	/// Latitude and longitude of the nodes.
+	points: Vec<(f64, f64)>,
+	/// Distance in meters.
+	distance: u64,
+	/// Max speed of the arcs in km/h.
+	max_speed: u64,
+	/// Travel time in seconds.
+	time: f64
+}
+
+/// A path through the graph with coordinates, split into segments.
+pub struct Route {
+	segments: Vec<RouteSegment>
+}
+
+impl Route {
+
+	/// Returns the distance of the route in meters.
+	pub fn distance(&self) -> u64 {
+		self.segments.iter().map(|segment| segment.distance).sum()
+	}
+
+	/// Returns the travel time of the route in seconds.
+	pub fn time(&self) -> f64 {
+		self.segments.iter().map(|segment| segment.time).sum()
+	}
+
+	/// Returns the number of points in all segments.
+	pub fn num_points(&self) -> usize {
+		self.segments.iter().map(|segment| segment.points.len()).sum()
+	}
+
+	/// Simplify the segments with the Douglas-Peucker algorithm.
+	///
+	/// Points closer than the tolerance in meters to the simplified line
+	/// are removed. The first and last point of each segment are kept.
+	pub fn simplify(&mut self, tolerance: f64) {
+		for segment in self.segments.iter_mut() {
+			let mut keep = vec![false; segment.points.len()];
+			douglas_peucker(&segment.points, 0, segment.points.len() - 1, tolerance, &mut keep);
+
+			segment.points = segment.points.iter().zip(keep.into_iter())
+				.filter(|&(_, keep)| keep)
+				.map(|(&point, _)| point)
+				.collect();
+		}
+	}
+
+	/// Write the route in the given format.
+	pub fn write<W: Write>(&self, format: RouteFormat, out: &mut W) -> IOResult<()> {
+		match format {
+			RouteFormat::GeoJson => self.write_geojson(out),
+			RouteFormat::Gpx => self.write_gpx(out),
+			RouteFormat::Kml => self.write_kml(out),
+			RouteFormat::Mapbb => self.write_mapbb(out)
+		}
+	}
+
+	/// Write the route to the file in the given format, by default in the
+	/// format given by its extension.
+	pub fn write_to_file(&self, name: &str, format: Option<RouteFormat>) -> Result<(), Error> {
+		let format = try!(RouteFormat::for_file(name, format));
+
+		let mut out = BufWriter::new(try!(File::create(name)));
+		try!(self.write(format, &mut out));
+		try!(out.flush());
+
+		Ok(())
+	}
+
+	/// Returns the cumulative distance and time at the end of each segment.
+	fn cumulative(&self) -> Vec<(u64, f64)> {
+		let (mut distance, mut time) = (0, 0.0);
+
+		self.segments.iter()
+			.map(|segment| {
+				distance += segment.distance;
+				time += segment.time;
+				(distance, time)
+			})
+			.collect()
+	}
+
+	/// Write all points as one line, simplify the route first to reduce the points.
+	fn write_mapbb<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "[map]"));
+
+		let mut first = true;
+		for (index, segment) in self.segments.iter().enumerate() {
+			// Segments start with the last point of the previous segment
+			for &(latitude, longitude) in segment.points.iter().skip(if index == 0 { 0 } else { 1 }) {
+				if !first {
+					try!(write!(out, " "));
+				}
+				first = false;
+
+				try!(write!(out, "{:.4},{:.4}", latitude, longitude));
+			}
+		}
+
+		write!(out, "[/map]\n")
+	}
+
+	/// Write a feature collection with a line string for each segment.
+	fn write_geojson<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "{{\"type\":\"FeatureCollection\",\"features\":["));
+
+		for (index, (segment, (distance, time))) in self.segments.iter().zip(self.cumulative()).enumerate() {
+			if index != 0 {
+				try!(write!(out, ","));
+			}
+
+			try!(write!(out, "{{\"type\":\"Feature\",\"geometry\":{{\"type\":\"LineString\",\"coordinates\":["));
+			for (point_index, &(latitude, longitude)) in segment.points.iter().enumerate() {
+				if point_index != 0 {
+					try!(write!(out, ","));
+				}
+				try!(write!(out, "[{:.6},{:.6}]", longitude, latitude));
+			}
+
+			try!(write!(out, "]}},\"properties\":{{\"distance\":{},\"max_speed\":{},\"time\":{:.1},\
+				\"cumulative_distance\":{},\"cumulative_time\":{:.1}}}}}",
+				segment.distance, segment.max_speed, segment.time, distance, time));
+		}
+
+		write!(out, "]}}\n")
+	}
+
+	/// Write a track with a track segment for each segment.
+	fn write_gpx<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+		try!(write!(out, "<gpx version=\"1.1\" creator=\"ex13\" xmlns=\"http://www.topografix.com/GPX/1/1\" \
+			xmlns:ex13=\"{}\">\n", GPX_EXTENSIONS_NAMESPACE));
+		try!(write!(out, "<trk>\n<name>Route</name>\n"));
+
+		for (segment, (distance, time)) in self.segments.iter().zip(self.cumulative()) {
+			try!(write!(out, "<trkseg>\n"));
+			for &(latitude, longitude) in segment.points.iter() {
+				try!(write!(out, "<trkpt lat=\"{:.6}\" lon=\"{:.6}\"/>\n", latitude, longitude));
+			}
+
+			try!(write!(out, "<extensions><ex13:distance>{}</ex13:distance><ex13:max_speed>{}</ex13:max_speed>\
+				<ex13:time>{:.1}</ex13:time><ex13:cumulative_distance>{}</ex13:cumulative_distance>\
+				<ex13:cumulative_time>{:.1}</ex13:cumulative_time></extensions>\n",
+				segment.distance, segment.max_speed, segment.time, distance, time));
+			try!(write!(out, "</trkseg>\n"));
+		}
+
+		write!(out, "</trk>\n</gpx>\n")
+	}
+
+	/// Write a document with a placemark for each segment.
+	fn write_kml<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+		try!(write!(out, "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n<name>Route</name>\n"));
+
+		for (index, (segment, (distance, time))) in self.segments.iter().zip(self.cumulative()).enumerate() {
+			try!(write!(out, "<Placemark>\n<name>Segment {}</name>\n<ExtendedData>\n", index + 1));
+
+			let properties = [("distance", format!("{}", segment.distance)),
+				("max_speed", format!("{}", segment.max_speed)),
+				("time", format!("{:.1}", segment.time)),
+				("cumulative_distance", format!("{}", distance)),
+				("cumulative_time", format!("{:.1}", time))];
+			for &(name, ref value) in properties.iter() {
+				try!(write!(out, "<Data name=\"{}\"><value>{}</value></Data>\n", name, value));
+			}
+
+			try!(write!(out, "</ExtendedData>\n<LineString><coordinates>"));
+			for (point_index, &(latitude, longitude)) in segment.points.iter().enumerate() {
+				if point_index != 0 {
+					try!(write!(out, " "));
+				}
+				try!(write!(out, "{:.6},{:.6}", longitude, latitude));
+			}
+			try!(write!(out, "</coordinates></LineString>\n</Placemark>\n"));
+		}
+
+		write!(out, "</Document>\n</kml>\n")
+	}
+}
+
+/// Mark the points between first and last to keep with the Douglas-Peucker algorithm.
+fn douglas_peucker(points: &Vec<(f64, f64)>, first: usize, last: usize, tolerance: f64, keep: &mut Vec<bool>) {
+	keep[first] = true;
+	keep[last] = true;
+
+	let mut furthest = (0.0, first);
+	for index in first + 1 .. last {
+		let distance = line_distance(points[index], points[first], points[last]);
+		if distance > furthest.0 {
+			furthest = (distance, index);
+		}
+	}
+
+	if furthest.0 > tolerance {
+		douglas_peucker(points, first, furthest.1, tolerance, keep);
+		douglas_peucker(points, furthest.1, last, tolerance, keep);
+	}
+}
+
+/// Returns the distance in meters of the point to the line from start to end.
+///
+/// Uses an equirectangular projection, which is exact enough for short lines.
+fn line_distance(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+	let scale = start.0.to_radians().cos();
+	let project = |(latitude, longitude): (f64, f64)| {
+		(longitude.to_radians() * scale * EARTH_RADIUS, latitude.to_radians() * EARTH_RADIUS)
+	};
+
+	let (x, y) = project(point);
+	let (x1, y1) = project(start);
+	let (x2, y2) = project(end);
+	let (dx, dy) = (x2 - x1, y2 - y1);
+
+	let length = dx * dx + dy * dy;
+	if length == 0.0 {
+		return ((x - x1).powi(2) + (y - y1).powi(2)).sqrt();
+	}
+
+	// Distance to the closest point on the line
+	let t = (((x - x1) * dx + (y - y1) * dy) / length).max(0.0).min(1.0);
+	((x - x1 - t * dx).powi(2) + (y - y1 - t * dy).powi(2)).sqrt()
+}
+
+impl Graph {
+
+	/// Generate the route along the given nodes.
+	///
+	/// The cheapest arc is used between consecutive nodes,
+	/// the travel time is computed with the given max vehicle speed.
+	pub fn generate_route(&self, path: &Vec<usize>, max_speed: u64) -> Result<Route, Error> {
+		let mut arcs = Vec::with_capacity(path.len());
+
+		for nodes in path.windows(2) {
+			let cheapest = self.arcs(nodes[0])
+				.filter(|&arc| self.arc_heads[arc] == nodes[1])
+				.fold(None, |cheapest: Option<usize>, arc| match cheapest {
+					Some(cheapest) if self.arc_costs[cheapest] <= self.arc_costs[arc] => Some(cheapest),
+					_ => Some(arc)
+				});
+
+			match cheapest {
+				Some(arc) => { arcs.push(arc); },
+				None => { return Err(Error::from(format!("Invalid route! (No arc from {} to {})", nodes[0], nodes[1]))); }
+			}
+		}
+
+		match path.first() {
+			Some(&start_node) => Ok(self.route_along_arcs(start_node, &arcs, max_speed)),
+			None => Err(Error::from("Invalid route! (Empty path)"))
+		}
+	}
+
+	/// Generate the route to the end node of the shortest paths.
+	pub fn generate_route_to(&self, paths: &ShortestPaths, end_node: usize, max_speed: u64) -> Route {
+		let mut arcs = Vec::new();
+
+		let mut node = end_node;
+		while let Some((tail_node, arc)) = paths.traceback_arcs[node] {
+			arcs.push(arc);
+			node = tail_node;
+		}
+		arcs.reverse();
+
+		self.route_along_arcs(node, &arcs, max_speed)
+	}
+
+	/// Split the arcs starting at the start node into segments with the same max speed.
+	fn route_along_arcs(&self, start_node: usize, arcs: &Vec<usize>, max_speed: u64) -> Route {
+		let point = |node: usize| (self.nodes[node].latitude, self.nodes[node].longitude);
+		let mut segments: Vec<RouteSegment> = Vec::new();
+
+		let mut node = start_node;
+		for &arc in arcs.iter() {
+			let head_node = self.arc_heads[arc];
+
+			let continues = match segments.last() {
+				Some(segment) => segment.max_speed == self.arc_max_speeds[arc],
+				None => false
+			};
+			if !continues {
+				segments.push(RouteSegment {
+					points: vec![point(node)],
+					distance: 0,
+					max_speed: self.arc_max_speeds[arc],
+					time: 0.0
+				});
+			}
+
+			let segment = segments.last_mut().unwrap();
+			segment.points.push(point(head_node));
+			segment.distance += self.arc_distances[arc];
+			segment.time += self.arc_travel_time(arc, max_speed);
+
+			node = head_node;
+		}
+
+		Route {
+			segments: segments
+		}
+	}
+}
+
+#[test]
+fn test_route_segments() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	// 0 -> 3 with 100 km/h, 3 -> 4 and 4 -> 5 with 50 km/h
+	let route = graph.generate_route(&vec![0, 3, 4, 5], 130).unwrap();
+	assert_eq!(2, route.segments.len());
+	assert_eq!(vec![(48.0, 7.8), (48.02, 7.8)], route.segments[0].points);
+	assert_eq!(2900, route.segments[1].distance);
+	assert_eq!(5200, route.distance());
+	assert!((route.time() - (2300.0 * 3.6 / 100.0 + 2900.0 * 3.6 / 50.0)).abs() < 1e-9);
+	assert_eq!(4, route.num_points() - route.segments.len() + 1);
+
+	assert!(graph.generate_route(&vec![0, 5], 130).is_err());
+
+	// The same route as traced from the shortest paths
+	let paths = graph.compute_shortest_paths(0);
+	let route = graph.generate_route_to(&paths, 5, 130);
+	let path = graph.compute_shortest_path_dijkstra(0, 5, 130).unwrap();
+	assert_eq!(path.0 as u64, route.distance());
+	assert_eq!(path.2.len(), route.num_points() - route.segments.len() + 1);
+}
+
+#[test]
+fn test_route_simplify() {
+	let mut route = Route {
+		segments: vec![RouteSegment {
+			points: vec![(48.0, 7.8), (48.00001, 7.805), (48.0, 7.81), (48.01, 7.815), (48.0, 7.82)],
+			distance: 0,
+			max_speed: 50,
+			time: 0.0
+		}]
+	};
+
+	route.simplify(5.0);
+	assert_eq!(vec![(48.0, 7.8), (48.0, 7.81), (48.01, 7.815), (48.0, 7.82)], route.segments[0].points);
+
+	route.simplify(2000.0);
+	assert_eq!(vec![(48.0, 7.8), (48.0, 7.82)], route.segments[0].points);
+}
+
+#[test]
+fn test_route_formats() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let route = graph.generate_route(&vec![0, 3, 4], 130).unwrap();
+
+	let mut geojson = Vec::new();
+	route.write(RouteFormat::GeoJson, &mut geojson).unwrap();
+	let geojson = String::from_utf8(geojson).unwrap();
+	assert!(geojson.starts_with("{\"type\":\"FeatureCollection\",\"features\":[{\"type\":\"Feature\",\
+		\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[7.800000,48.000000],[7.800000,48.020000]]}"));
+	assert!(geojson.contains("\"distance\":1500,\"max_speed\":50,\"time\":108.0,\
+		\"cumulative_distance\":3800,\"cumulative_time\":190.8"));
+
+	let mut gpx = Vec::new();
+	route.write(RouteFormat::Gpx, &mut gpx).unwrap();
+	let gpx = String::from_utf8(gpx).unwrap();
+	assert_eq!(2, gpx.matches("<trkseg>").count());
+	assert!(gpx.contains("<trkpt lat=\"48.020000\" lon=\"7.820000\"/>"));
+
+	// The extensions must not be in the GPX namespace
+	assert!(gpx.contains(&format!(" xmlns:ex13=\"{}\">", GPX_EXTENSIONS_NAMESPACE)));
+	assert!(gpx.contains("<extensions><ex13:distance>"));
+	assert!(!gpx.contains("<distance>"));
+
+	let mut kml = Vec::new();
+	route.write(RouteFormat::Kml, &mut kml).unwrap();
+	let kml = String::from_utf8(kml).unwrap();
+	assert_eq!(2, kml.matches("<Placemark>").count());
+	assert!(kml.contains("<coordinates>7.800000,48.020000 7.820000,48.020000</coordinates>"));
+
+	let mut mapbb = Vec::new();
+	route.write(RouteFormat::Mapbb, &mut mapbb).unwrap();
+	assert_eq!("[map]48.0000,7.8000 48.0200,7.8000 48.0200,7.8200[/map]\n", String::from_utf8(mapbb).unwrap());
+
+	assert_eq!(Some(RouteFormat::Kml), RouteFormat::from_file_name("route.KML"));
+	assert_eq!(Some(RouteFormat::Mapbb), RouteFormat::from_file_name("route.map"));
+	assert_eq!(None, RouteFormat::from_file_name("route.txt"));
+	assert_eq!(RouteFormat::Gpx, RouteFormat::for_file("route.txt", Some(RouteFormat::Gpx)).unwrap());
+	assert!(RouteFormat::for_file("route.txt", None).is_err());
+}
\ No newline at end of file
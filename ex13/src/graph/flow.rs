@@ -0,0 +1,301 @@
+use super::Graph;
+
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+/// Length of a vehicle in meters, including the gap to the vehicle in front of it when standing.
+const VEHICLE_LENGTH: f64 = 7.5;
+/// Time gap in seconds between vehicles following each other.
+const TIME_GAP: f64 = 1.8;
+
+/// Capacity of an arc derived from its max speed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Capacity {
+	/// The max speed in km/h itself.
+	MaxSpeed,
+	/// Vehicles per hour which fit on a lane when driving at the max speed
+	/// with the time gap between them.
+	VehiclesPerHour
+}
+
+impl Capacity {
+	fn of(&self, max_speed: u64) -> u64 {
+		match *self {
+			Capacity::MaxSpeed => max_speed,
+			Capacity::VehiclesPerHour => {
+				let speed = max_speed as f64 / 3.6;
+				(3600.0 * speed / (VEHICLE_LENGTH + speed * TIME_GAP)).round() as u64
+			}
+		}
+	}
+}
+
+/// Maximum flow between a set of sources and a set of sinks with its minimum cut.
+pub struct MaxFlow {
+	flow: u64,
+	arc_flows: Vec<u64>,
+	source_side: Vec<bool>,
+	cut_arcs: Vec<usize>
+}
+
+impl MaxFlow {
+
+	/// Returns the value of the flow.
+	pub fn flow(&self) -> u64 {
+		self.flow
+	}
+
+	/// Returns the flow over the arc.
+	pub fn arc_flow(&self, arc: usize) -> u64 {
+		self.arc_flows[arc]
+	}
+
+	/// Returns if the node is on the side of the sources of the minimum cut.
+	pub fn is_source_side(&self, node: usize) -> bool {
+		self.source_side[node]
+	}
+
+	/// Returns the arcs from the source side to the sink side of the minimum
+	/// cut in ascending order. Their capacities sum up to the flow.
+	pub fn cut_arcs(&self) -> &Vec<usize> {
+		&self.cut_arcs
+	}
+}
+
+/// Residual network with an arc in each direction for each arc of the graph.
+///
+/// The arcs are stored in pairs, so that the opposite arc of an arc
+/// has the id of the arc with the lowest bit flipped.
+struct Residual {
+	offsets: Vec<usize>,
+	arcs: Vec<usize>,
+	heads: Vec<usize>,
+	capacities: Vec<u64>
+}
+
+impl Residual {
+
+	/// Create the network from arcs given as tail node, head node and capacity.
+	fn new(node_count: usize, arcs: &Vec<(usize, usize, u64)>) -> Residual {
+		let mut heads = Vec::with_capacity(2 * arcs.len());
+		let mut capacities = Vec::with_capacity(2 * arcs.len());
+		let mut degrees = vec![0; node_count + 1];
+
+		for &(tail_node, head_node, capacity) in arcs.iter() {
+			heads.push(head_node);
+			capacities.push(capacity);
+			heads.push(tail_node);
+			capacities.push(0);
+			degrees[tail_node + 1] += 1;
+			degrees[head_node + 1] += 1;
+		}
+
+		let mut offsets = degrees;
+		for node in 0..node_count {
+			offsets[node + 1] += offsets[node];
+		}
+
+		// Counting sort of the residual arcs by their tail node
+		let mut positions = offsets.clone();
+		let mut node_arcs = vec![0; 2 * arcs.len()];
+		for (arc, &(tail_node, head_node, _)) in arcs.iter().enumerate() {
+			node_arcs[positions[tail_node]] = 2 * arc;
+			positions[tail_node] += 1;
+			node_arcs[positions[head_node]] = 2 * arc + 1;
+			positions[head_node] += 1;
+		}
+
+		Residual {
+			offsets: offsets,
+			arcs: node_arcs,
+			heads: heads,
+			capacities: capacities
+		}
+	}
+
+	fn num_nodes(&self) -> usize {
+		self.offsets.len() - 1
+	}
+
+	/// Returns the distance in arcs of each node from the source, only following arcs with capacity left.
+	fn levels(&self, source: usize) -> Vec<Option<usize>> {
+		let mut levels = vec![None; self.num_nodes()];
+		let mut queue = VecDeque::new();
+
+		levels[source] = Some(0);
+		queue.push_back(source);
+
+		while let Some(node) = queue.pop_front() {
+			for &arc in self.arcs[self.offsets[node] .. self.offsets[node + 1]].iter() {
+				let head_node = self.heads[arc];
+				if self.capacities[arc] > 0 && levels[head_node].is_none() {
+					levels[head_node] = Some(levels[node].unwrap() + 1);
+					queue.push_back(head_node);
+				}
+			}
+		}
+
+		return levels;
+	}
+
+	/// Compute the maximum flow with Dinic's algorithm, leaving the residual capacities.
+	fn compute_max_flow(&mut self, source: usize, sink: usize) -> u64 {
+		let mut flow = 0;
+
+		loop {
+			let levels = self.levels(source);
+			if levels[sink].is_none() {
+				return flow;
+			}
+
+			// Index of the next arc to try for each node, arcs before it lead to dead ends
+			let mut next_arcs = self.offsets[..self.num_nodes()].to_vec();
+			let mut path: Vec<usize> = Vec::new();
+
+			loop {
+				let node = match path.last() {
+					Some(&arc) => self.heads[arc],
+					None => source
+				};
+
+				if node == sink {
+					let bottleneck = path.iter().map(|&arc| self.capacities[arc]).min().unwrap();
+					for &arc in path.iter() {
+						self.capacities[arc] -= bottleneck;
+						self.capacities[arc ^ 1] = self.capacities[arc ^ 1].saturating_add(bottleneck);
+					}
+
+					flow += bottleneck;
+					path.clear();
+					continue;
+				}
+
+				// Advance over an arc to the next level with capacity left
+				let mut advanced = false;
+				while next_arcs[node] < self.offsets[node + 1] {
+					let arc = self.arcs[next_arcs[node]];
+					let head_node = self.heads[arc];
+					if self.capacities[arc] > 0 && levels[head_node] == levels[node].map(|level| level + 1) {
+						path.push(arc);
+						advanced = true;
+						break;
+					}
+					next_arcs[node] += 1;
+				}
+
+				if advanced {
+					continue;
+				}
+
+				// Retreat from the dead end
+				match path.pop() {
+					Some(arc) => {
+						let tail_node = self.heads[arc ^ 1];
+						next_arcs[tail_node] += 1;
+					},
+					None => { break; }
+				}
+			}
+		}
+	}
+}
+
+impl Graph {
+
+	/// Compute the maximum flow from the sources to the sinks with Dinic's algorithm.
+	///
+	/// The capacity of each arc is derived from its max speed. The flow may
+	/// leave each source and enter each sink without limit. Returns None if
+	/// a node is both source and sink, since the flow would be unlimited.
+	pub fn compute_max_flow(&self, sources: &Vec<usize>, sinks: &Vec<usize>, capacity: Capacity) -> Option<MaxFlow> {
+		if sources.iter().any(|source| sinks.contains(source)) {
+			return None;
+		}
+
+		let node_count = self.num_nodes();
+		let (source, sink) = (node_count, node_count + 1);
+
+		let mut arcs = Vec::with_capacity(self.num_arcs() + sources.len() + sinks.len());
+		for node in 0..node_count {
+			for arc in self.arcs(node) {
+				arcs.push((node, self.arc_heads[arc], capacity.of(self.arc_max_speeds[arc])));
+			}
+		}
+
+		// Connect all sources to a super source and all sinks to a super sink
+		for &node in sources.iter() {
+			arcs.push((source, node, u64::MAX));
+		}
+		for &node in sinks.iter() {
+			arcs.push((node, sink, u64::MAX));
+		}
+
+		let mut residual = Residual::new(node_count + 2, &arcs);
+		let flow = residual.compute_max_flow(source, sink);
+
+		// The nodes still reachable from the source form the source side of a minimum cut
+		let levels = residual.levels(source);
+		let source_side: Vec<bool> = (0..node_count).map(|node| levels[node].is_some()).collect();
+
+		let arc_flows: Vec<u64> = (0..self.num_arcs())
+			.map(|arc| arcs[arc].2 - residual.capacities[2 * arc])
+			.collect();
+
+		let cut_arcs = (0..self.num_arcs())
+			.filter(|&arc| source_side[arcs[arc].0] && !source_side[arcs[arc].1])
+			.collect();
+
+		Some(MaxFlow {
+			flow: flow,
+			arc_flows: arc_flows,
+			source_side: source_side,
+			cut_arcs: cut_arcs
+		})
+	}
+}
+
+#[test]
+fn test_capacity() {
+	assert_eq!(50, Capacity::MaxSpeed.of(50));
+	assert_eq!(0, Capacity::VehiclesPerHour.of(0));
+
+	// About one vehicle every two seconds at high speeds
+	assert_eq!(1538, Capacity::VehiclesPerHour.of(50));
+	assert!(Capacity::VehiclesPerHour.of(130) > Capacity::VehiclesPerHour.of(100));
+	assert!(Capacity::VehiclesPerHour.of(130) < 2000);
+}
+
+#[test]
+fn test_max_flow() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	// The arcs out of node 0 have a capacity of 50 + 30 + 100
+	let flow = graph.compute_max_flow(&vec![0], &vec![5], Capacity::MaxSpeed).unwrap();
+	assert_eq!(180, flow.flow());
+	assert_eq!(vec![(0, 1), (0, 2), (0, 3)], flow.cut_arcs().iter()
+		.map(|&arc| graph.arc_nodes(arc))
+		.collect::<Vec<(usize, usize)>>());
+	assert!(flow.is_source_side(0) && !flow.is_source_side(1));
+
+	// The arcs into node 5 have a capacity of 100 + 50 + 30 + 50
+	let flow = graph.compute_max_flow(&vec![0, 1, 2, 3, 4, 6], &vec![5], Capacity::MaxSpeed).unwrap();
+	assert_eq!(230, flow.flow());
+	assert_eq!(4, flow.cut_arcs().len());
+
+	// Flow is conserved at all inner nodes
+	let flow = graph.compute_max_flow(&vec![0, 1], &vec![4, 5], Capacity::VehiclesPerHour).unwrap();
+	let cut_capacity: u64 = flow.cut_arcs().iter()
+		.map(|&arc| Capacity::VehiclesPerHour.of(graph.arc_max_speeds[arc]))
+		.sum();
+	assert_eq!(flow.flow(), cut_capacity);
+
+	for node in 2..4 {
+		let inflow: u64 = graph.reverse_arcs(node).map(|index| flow.arc_flow(graph.reverse_arc_ids[index])).sum();
+		let outflow: u64 = graph.arcs(node).map(|arc| flow.arc_flow(arc)).sum();
+		assert_eq!(inflow, outflow);
+	}
+
+	// Node 6 cannot be reached
+	assert_eq!(0, graph.compute_max_flow(&vec![5], &vec![6], Capacity::MaxSpeed).unwrap().flow());
+	assert!(graph.compute_max_flow(&vec![5], &vec![6, 5], Capacity::MaxSpeed).is_none());
+}
\ No newline at end of file
@@ -0,0 +1,222 @@
+extern crate flate2;
+
+use super::Error;
+use super::zip::ZipArchive;
+
+use self::flate2::read::MultiGzDecoder;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek};
+use std::result::Result;
+use std::vec::Vec;
+
+/// Number of lines between two progress reports.
+pub const PROGRESS_INTERVAL: usize = 100000;
+
+/// Magic bytes at the start of zip archives.
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+/// Magic bytes at the start of gzip streams.
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+/// Container of a graph in text format.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InputFormat {
+	/// Plain text.
+	Text,
+	/// Gzip stream of the text.
+	Gzip,
+	/// Zip archive with the text as its only file.
+	Zip
+}
+
+impl InputFormat {
+
+	/// Returns the format for the extension of the file name, if it is a known one.
+	pub fn from_file_name(name: &str) -> Option<InputFormat> {
+		let name = name.to_lowercase();
+
+		if name.ends_with(".zip") {
+			Some(InputFormat::Zip)
+		} else if name.ends_with(".gz") {
+			Some(InputFormat::Gzip)
+		} else if name.ends_with(".txt") {
+			Some(InputFormat::Text)
+		} else {
+			None
+		}
+	}
+
+	/// Returns the format given by the magic bytes at the start of the data.
+	pub fn from_magic_bytes(bytes: &[u8]) -> InputFormat {
+		if bytes.starts_with(ZIP_MAGIC) {
+			InputFormat::Zip
+		} else if bytes.starts_with(GZIP_MAGIC) {
+			InputFormat::Gzip
+		} else {
+			InputFormat::Text
+		}
+	}
+}
+
+/// State of reading a graph, reported while reading.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Progress {
+	/// Lines read so far, including comments.
+	pub lines: usize,
+	pub nodes: usize,
+	pub arcs: usize,
+	/// Number of nodes given at the start of the file.
+	pub total_nodes: usize,
+	/// Number of arcs given at the start of the file.
+	pub total_arcs: usize
+}
+
+/// Open the graph file and pass its text to the function.
+///
+/// The file name "-" reads from stdin. The format is given by the extension
+/// of the file name or by its magic bytes if the extension is unknown.
+pub(super) fn with_graph_text<T, F>(name: &str, function: F) -> Result<T, Error>
+		where F: FnOnce(&mut dyn BufRead) -> Result<T, Error> {
+	if name == "-" {
+		let stdin = io::stdin();
+		let input = stdin.lock();
+		return with_text(input, None, function);
+	}
+
+	let file = try!(File::open(name));
+	match InputFormat::from_file_name(name) {
+		Some(InputFormat::Zip) => with_zip_text(file, function),
+		format => with_text(BufReader::new(file), format, function)
+	}
+}
+
+/// Pass the text of the input in the given format to the function, the
+/// format is detected by its magic bytes if it is not given.
+pub(super) fn with_text<R, T, F>(mut input: R, format: Option<InputFormat>, function: F) -> Result<T, Error>
+		where R: BufRead, F: FnOnce(&mut dyn BufRead) -> Result<T, Error> {
+	let format = match format {
+		Some(format) => format,
+		None => InputFormat::from_magic_bytes(try!(input.fill_buf()))
+	};
+
+	match format {
+		InputFormat::Text => function(&mut input),
+		InputFormat::Gzip => function(&mut BufReader::new(MultiGzDecoder::new(input))),
+		InputFormat::Zip => {
+			// Zip archives can only be read with random access
+			let mut bytes = Vec::new();
+			try!(input.read_to_end(&mut bytes));
+			with_zip_text(Cursor::new(bytes), function)
+		}
+	}
+}
+
+/// Pass the only file of the zip archive to the function.
+///
+/// Archives with more than one file are rejected, since each file holds
+/// a whole graph with its own node and arc counts.
+fn with_zip_text<R, T, F>(input: R, function: F) -> Result<T, Error>
+		where R: Read + Seek, F: FnOnce(&mut dyn BufRead) -> Result<T, Error> {
+	let mut archive = try!(ZipArchive::new(input));
+
+	let mut files = Vec::new();
+	for index in 0 .. archive.len() {
+		if !try!(archive.by_index(index)).name().ends_with('/') {
+			files.push(index);
+		}
+	}
+
+	match files.len() {
+		0 => Err(Error::from("Invalid graph file! (zip archive without file)")),
+		1 => {
+			let entry = try!(archive.by_index(files[0]));
+			function(&mut BufReader::new(entry))
+		},
+		count => Err(Error::from(format!("Invalid graph file! (zip archive with {} files)", count)))
+	}
+}
+
+#[cfg(test)]
+use super::{Graph, LoadMode};
+
+#[cfg(test)]
+fn write_test_inputs() -> (String, String, String) {
+	use self::flate2::Compression;
+	use self::flate2::write::GzEncoder;
+	use std::io::Write;
+
+	let mut text = String::new();
+	with_graph_text("graphs/test.zip", |input| Ok(try!(input.read_to_string(&mut text)))).unwrap();
+
+	let directory = ::std::env::temp_dir();
+	let text_name = directory.join("ex13_test_input.txt").to_str().unwrap().to_owned();
+	let gzip_name = directory.join("ex13_test_input.txt.gz").to_str().unwrap().to_owned();
+	let unknown_name = directory.join("ex13_test_input.graph").to_str().unwrap().to_owned();
+
+	File::create(&text_name).unwrap().write_all(text.as_bytes()).unwrap();
+	for name in [&gzip_name, &unknown_name].iter() {
+		let mut encoder = GzEncoder::new(File::create(name).unwrap(), Compression::default());
+		encoder.write_all(text.as_bytes()).unwrap();
+		encoder.finish().unwrap();
+	}
+
+	return (text_name, gzip_name, unknown_name);
+}
+
+#[test]
+fn test_input_formats() {
+	let expected = format!("{}", Graph::read_graph_from_file("graphs/test.zip").unwrap());
+	let (text_name, gzip_name, unknown_name) = write_test_inputs();
+
+	// The gzip stream without known extension is detected by its magic bytes
+	for name in [text_name, gzip_name, unknown_name].iter() {
+		assert_eq!(expected, format!("{}", Graph::read_graph_from_file(name).unwrap()));
+	}
+
+	let mut bytes = Vec::new();
+	File::open("graphs/test.zip").unwrap().read_to_end(&mut bytes).unwrap();
+	let graph = Graph::read_graph_from_reader(Cursor::new(bytes), LoadMode::Directed, |_| { }).unwrap();
+	assert_eq!(expected, format!("{}", graph));
+
+	assert_eq!(Some(InputFormat::Gzip), InputFormat::from_file_name("graph.TXT.GZ"));
+	assert_eq!(None, InputFormat::from_file_name("graph.graph"));
+	assert_eq!(InputFormat::Text, InputFormat::from_magic_bytes(b"# Number of nodes."));
+	assert_eq!(InputFormat::Text, InputFormat::from_magic_bytes(b""));
+}
+
+#[test]
+fn test_input_progress() {
+	let text = "# Number of nodes.\n2\n# Number of arcs.\n1\n0 48.0 7.8\n1 48.0 7.82\n0 1 1600 50\n";
+
+	let mut reports = Vec::new();
+	let graph = Graph::read_graph_from_reader(text.as_bytes(), LoadMode::Undirected, |progress| {
+		reports.push(*progress);
+	}).unwrap();
+	assert_eq!(2, graph.num_arcs());
+
+	// Small graphs only report when done
+	assert_eq!(vec![Progress { lines: 7, nodes: 2, arcs: 1, total_nodes: 2, total_arcs: 1 }], reports);
+
+	assert!(Graph::read_graph_from_file("graphs/missing.txt").is_err());
+}
+
+#[test]
+fn test_input_zip_files() {
+	use super::zip::ZipWriter;
+	use super::zip::write::FileOptions;
+	use std::io::Write;
+
+	let text = "# Number of nodes.\n1\n# Number of arcs.\n0\n0 48.0 7.8\n";
+	let name = ::std::env::temp_dir().join("ex13_test_input_files.zip").to_str().unwrap().to_owned();
+
+	let mut archive = ZipWriter::new(File::create(&name).unwrap());
+	for entry_name in ["first.txt", "second.txt"].iter() {
+		archive.start_file(*entry_name, FileOptions::default()).unwrap();
+		archive.write_all(text.as_bytes()).unwrap();
+	}
+	archive.finish().unwrap();
+
+	// The second graph must not be left out silently
+	assert!(Graph::read_graph_from_file(&name).is_err());
+	assert!(Graph::validate_file(&name).is_err());
+}
\ No newline at end of file
@@ -0,0 +1,509 @@
+use super::{Error, Graph, RouteFormat};
+use super::export::GPX_EXTENSIONS_NAMESPACE;
+
+use std::cmp::max;
+use std::f64;
+use std::fs::File;
+use std::io::{BufWriter, Result as IOResult, Write};
+use std::result::Result;
+use std::vec::Vec;
+
+/// Shape of the boundary polygon of an isochrone.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hull {
+	Convex,
+	/// Concave hull over the given number of nearest neighbours per step,
+	/// fewer neighbours follow the nodes more closely.
+	Concave(usize)
+}
+
+/// Area reachable from a start node within a cost budget.
+pub struct Isochrone {
+	max_cost: f64,
+	nodes: Box<Vec<usize>>,
+	/// Latitude and longitude of the polygon in counterclockwise order.
+	boundary: Vec<(f64, f64)>
+}
+
+impl Isochrone {
+
+	/// Returns the cost budget of the isochrone.
+	pub fn max_cost(&self) -> f64 {
+		self.max_cost
+	}
+
+	/// Returns the reachable nodes in ascending order.
+	pub fn nodes(&self) -> &Vec<usize> {
+		&self.nodes
+	}
+
+	/// Returns the latitude and longitude of the boundary polygon.
+	///
+	/// The polygon is not closed, the last point is not repeated.
+	pub fn boundary(&self) -> &Vec<(f64, f64)> {
+		&self.boundary
+	}
+
+	/// Write the boundary in the given format.
+	pub fn write<W: Write>(&self, format: RouteFormat, out: &mut W) -> IOResult<()> {
+		match format {
+			RouteFormat::GeoJson => self.write_geojson(out),
+			RouteFormat::Gpx => self.write_gpx(out),
+			RouteFormat::Kml => self.write_kml(out),
+			RouteFormat::Mapbb => self.write_mapbb(out)
+		}
+	}
+
+	/// Write the boundary to the file in the given format, by default in the
+	/// format given by its extension.
+	pub fn write_to_file(&self, name: &str, format: Option<RouteFormat>) -> Result<(), Error> {
+		let format = try!(RouteFormat::for_file(name, format));
+
+		let mut out = BufWriter::new(try!(File::create(name)));
+		try!(self.write(format, &mut out));
+		try!(out.flush());
+
+		Ok(())
+	}
+
+	/// Returns the boundary with the first point repeated at the end.
+	fn closed_boundary(&self) -> Vec<(f64, f64)> {
+		let mut points = self.boundary.clone();
+		if let Some(&first) = self.boundary.first() {
+			points.push(first);
+		}
+		points
+	}
+
+	fn write_mapbb<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "[map]"));
+
+		for (index, &(latitude, longitude)) in self.closed_boundary().iter().enumerate() {
+			if index != 0 {
+				try!(write!(out, " "));
+			}
+			try!(write!(out, "{:.4},{:.4}", latitude, longitude));
+		}
+
+		write!(out, "[/map]\n")
+	}
+
+	/// Write a feature collection with a single polygon.
+	fn write_geojson<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "{{\"type\":\"FeatureCollection\",\"features\":[{{\"type\":\"Feature\",\
+			\"geometry\":{{\"type\":\"Polygon\",\"coordinates\":[["));
+
+		for (index, &(latitude, longitude)) in self.closed_boundary().iter().enumerate() {
+			if index != 0 {
+				try!(write!(out, ","));
+			}
+			try!(write!(out, "[{:.6},{:.6}]", longitude, latitude));
+		}
+
+		write!(out, "]]}},\"properties\":{{\"max_cost\":{:.1},\"nodes\":{}}}}}]}}\n", self.max_cost, self.nodes.len())
+	}
+
+	/// Write a track around the boundary, GPX has no polygons.
+	fn write_gpx<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+		try!(write!(out, "<gpx version=\"1.1\" creator=\"ex13\" xmlns=\"http://www.topografix.com/GPX/1/1\" \
+			xmlns:ex13=\"{}\">\n", GPX_EXTENSIONS_NAMESPACE));
+		try!(write!(out, "<trk>\n<name>Isochrone</name>\n<trkseg>\n"));
+
+		for &(latitude, longitude) in self.closed_boundary().iter() {
+			try!(write!(out, "<trkpt lat=\"{:.6}\" lon=\"{:.6}\"/>\n", latitude, longitude));
+		}
+
+		try!(write!(out, "<extensions><ex13:max_cost>{:.1}</ex13:max_cost><ex13:nodes>{}</ex13:nodes></extensions>\n",
+			self.max_cost, self.nodes.len()));
+		write!(out, "</trkseg>\n</trk>\n</gpx>\n")
+	}
+
+	/// Write a document with a polygon placemark.
+	fn write_kml<W: Write>(&self, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+		try!(write!(out, "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n<name>Isochrone</name>\n"));
+		try!(write!(out, "<Placemark>\n<name>Isochrone</name>\n<ExtendedData>\n"));
+		try!(write!(out, "<Data name=\"max_cost\"><value>{:.1}</value></Data>\n", self.max_cost));
+		try!(write!(out, "<Data name=\"nodes\"><value>{}</value></Data>\n", self.nodes.len()));
+		try!(write!(out, "</ExtendedData>\n<Polygon><outerBoundaryIs><LinearRing><coordinates>"));
+
+		for (index, &(latitude, longitude)) in self.closed_boundary().iter().enumerate() {
+			if index != 0 {
+				try!(write!(out, " "));
+			}
+			try!(write!(out, "{:.6},{:.6}", longitude, latitude));
+		}
+
+		try!(write!(out, "</coordinates></LinearRing></outerBoundaryIs></Polygon>\n</Placemark>\n"));
+		write!(out, "</Document>\n</kml>\n")
+	}
+}
+
+impl Graph {
+
+	/// Compute the area reachable from the start node with costs up to max_cost.
+	///
+	/// The costs are the arc costs, use set_arc_costs_to_travel_time for a
+	/// time budget. The boundary is the given hull of the reachable nodes.
+	pub fn compute_isochrone(&self, start_node: usize, max_cost: f64, hull: Hull) -> Isochrone {
+		let paths = self.compute_shortest_paths_within(start_node, max_cost);
+		let nodes: Vec<usize> = (0..self.num_nodes()).filter(|&node| paths.is_settled(node)).collect();
+
+		let mut points: Vec<(f64, f64)> = nodes.iter()
+			.map(|&node| (self.nodes[node].latitude, self.nodes[node].longitude))
+			.collect();
+		points.sort_by(|first, second| first.partial_cmp(second).unwrap());
+		points.dedup();
+
+		let boundary = match hull {
+			Hull::Convex => convex_hull(&points),
+			Hull::Concave(neighbours) => concave_hull(&points, neighbours)
+		};
+
+		Isochrone {
+			max_cost: max_cost,
+			nodes: Box::new(nodes),
+			boundary: boundary
+		}
+	}
+}
+
+/// Project latitude and longitude to planar coordinates, exact enough for small areas.
+fn project(points: &Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+	let latitude = points.iter().map(|point| point.0).sum::<f64>() / points.len() as f64;
+	let scale = latitude.to_radians().cos();
+
+	points.iter().map(|&(latitude, longitude)| (longitude * scale, latitude)).collect()
+}
+
+/// Returns a positive value if the points turn counterclockwise.
+fn cross(origin: (f64, f64), first: (f64, f64), second: (f64, f64)) -> f64 {
+	(first.0 - origin.0) * (second.1 - origin.1) - (first.1 - origin.1) * (second.0 - origin.0)
+}
+
+/// Returns if the segments cross each other, touching ends do not count.
+fn segments_cross(first: ((f64, f64), (f64, f64)), second: ((f64, f64), (f64, f64))) -> bool {
+	let side = |segment: ((f64, f64), (f64, f64)), point: (f64, f64)| cross(segment.0, segment.1, point);
+
+	side(first, second.0) * side(first, second.1) < 0.0 && side(second, first.0) * side(second, first.1) < 0.0
+}
+
+/// Returns if the point is inside or on the polygon.
+fn polygon_contains(polygon: &Vec<(f64, f64)>, point: (f64, f64)) -> bool {
+	let mut inside = false;
+
+	for index in 0..polygon.len() {
+		let start = polygon[index];
+		let end = polygon[(index + 1) % polygon.len()];
+
+		// On the edge
+		let length = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+		if cross(start, end, point).abs() <= 1e-12 * length.max(1.0)
+				&& point.0 >= start.0.min(end.0) && point.0 <= start.0.max(end.0)
+				&& point.1 >= start.1.min(end.1) && point.1 <= start.1.max(end.1) {
+			return true;
+		}
+
+		// Count the edges crossing a ray to the right of the point
+		if (start.1 > point.1) != (end.1 > point.1)
+				&& point.0 < start.0 + (point.1 - start.1) / (end.1 - start.1) * (end.0 - start.0) {
+			inside = !inside;
+		}
+	}
+
+	inside
+}
+
+/// Compute the convex hull of the points with Andrew's monotone chain.
+///
+/// The hull starts at the westernmost point and runs counterclockwise.
+fn convex_hull(points: &Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+	if points.len() < 3 {
+		return points.clone();
+	}
+
+	let projected = project(points);
+	let mut order: Vec<usize> = (0..points.len()).collect();
+	order.sort_by(|&first, &second| projected[first].partial_cmp(&projected[second]).unwrap());
+
+	let mut hull: Vec<usize> = Vec::with_capacity(points.len() + 1);
+	for pass in 0..2 {
+		// Lower hull from west to east, then upper hull back
+		let start = hull.len();
+		for step in 0..order.len() {
+			let index = match pass {
+				0 => order[step],
+				_ => order[order.len() - 1 - step]
+			};
+
+			while hull.len() >= start + 2
+					&& cross(projected[hull[hull.len() - 2]], projected[hull[hull.len() - 1]], projected[index]) <= 0.0 {
+				hull.pop();
+			}
+			hull.push(index);
+		}
+
+		// The last point is the first of the other half
+		hull.pop();
+	}
+
+	hull.iter().map(|&index| points[index]).collect()
+}
+
+/// Compute a concave hull of the points with the k-nearest neighbours algorithm.
+///
+/// Starting at the southernmost point, the hull moves to the neighbour with
+/// the sharpest right turn which does not cross the hull so far. If the hull
+/// gets stuck or misses points, the number of neighbours is increased. The
+/// convex hull is the result if all points are neighbours.
+fn concave_hull(points: &Vec<(f64, f64)>, neighbours: usize) -> Vec<(f64, f64)> {
+	if points.len() < 4 {
+		return convex_hull(points);
+	}
+
+	let projected = project(points);
+	let grid = PointGrid::new(&projected);
+
+	let mut neighbours = max(neighbours, 3);
+	while neighbours < points.len() - 1 {
+		if let Some(hull) = walk_concave_hull(&projected, &grid, neighbours) {
+			return hull.iter().map(|&index| points[index]).collect();
+		}
+		neighbours += 1;
+	}
+
+	convex_hull(points)
+}
+
+/// Walk around the points with the given number of neighbours, see concave_hull.
+fn walk_concave_hull(points: &Vec<(f64, f64)>, grid: &PointGrid, neighbours: usize) -> Option<Vec<usize>> {
+	let angle = |from: usize, to: usize| (points[to].1 - points[from].1).atan2(points[to].0 - points[from].0);
+	let clockwise = |from: f64, to: f64| (from - to).rem_euclid(2.0 * f64::consts::PI);
+
+	let first = (0..points.len())
+		.min_by(|&a, &b| (points[a].1, points[a].0).partial_cmp(&(points[b].1, points[b].0)).unwrap())
+		.unwrap();
+
+	let mut removed = vec![false; points.len()];
+	let mut hull = vec![first];
+	let mut current = first;
+	// Pretend to come from the west
+	let mut back_angle = f64::consts::PI;
+	removed[first] = true;
+
+	loop {
+		// The hull may be closed once it is a triangle
+		if hull.len() == 3 {
+			removed[first] = false;
+		}
+
+		let mut candidates = grid.k_nearest(points, current, neighbours, &removed);
+		let angles: Vec<f64> = candidates.iter().map(|&candidate| clockwise(back_angle, angle(current, candidate))).collect();
+		let mut order: Vec<usize> = (0..candidates.len()).collect();
+		order.sort_by(|&a, &b| angles[b].partial_cmp(&angles[a]).unwrap());
+		candidates = order.into_iter().map(|index| candidates[index]).collect();
+
+		let next = candidates.into_iter().find(|&candidate| {
+			// Skip the edges which share a point with the new edge
+			let first_edge = if candidate == first { 1 } else { 0 };
+			let last_edge = if hull.len() >= 2 { hull.len() - 2 } else { 0 };
+
+			(first_edge..last_edge).all(|edge| !segments_cross((points[current], points[candidate]),
+				(points[hull[edge]], points[hull[edge + 1]])))
+		});
+
+		let next = match next {
+			Some(next) => next,
+			None => { return None; }
+		};
+		if next == first {
+			break;
+		}
+
+		back_angle = angle(next, current);
+		removed[next] = true;
+		hull.push(next);
+		current = next;
+	}
+
+	let polygon: Vec<(f64, f64)> = hull.iter().map(|&index| points[index]).collect();
+	if points.iter().all(|&point| polygon_contains(&polygon, point)) {
+		Some(hull)
+	} else {
+		None
+	}
+}
+
+/// Uniform grid over points for nearest neighbour queries.
+struct PointGrid {
+	min: (f64, f64),
+	cell_size: f64,
+	columns: usize,
+	rows: usize,
+	cells: Vec<Vec<usize>>
+}
+
+impl PointGrid {
+
+	/// Create a grid with about one point per cell.
+	fn new(points: &Vec<(f64, f64)>) -> PointGrid {
+		let min_x = points.iter().map(|point| point.0).fold(f64::INFINITY, f64::min);
+		let min_y = points.iter().map(|point| point.1).fold(f64::INFINITY, f64::min);
+		let width = points.iter().map(|point| point.0).fold(f64::NEG_INFINITY, f64::max) - min_x;
+		let height = points.iter().map(|point| point.1).fold(f64::NEG_INFINITY, f64::max) - min_y;
+
+		let count = points.len() as f64;
+		let mut cell_size = (width * height / count).sqrt().max(width.max(height) / count);
+		if !(cell_size > 0.0) {
+			cell_size = 1.0;
+		}
+
+		let columns = (width / cell_size) as usize + 1;
+		let rows = (height / cell_size) as usize + 1;
+		let mut grid = PointGrid {
+			min: (min_x, min_y),
+			cell_size: cell_size,
+			columns: columns,
+			rows: rows,
+			cells: vec![Vec::new(); columns * rows]
+		};
+
+		for (index, &point) in points.iter().enumerate() {
+			let (column, row) = grid.cell(point);
+			grid.cells[row * columns + column].push(index);
+		}
+
+		grid
+	}
+
+	fn cell(&self, point: (f64, f64)) -> (usize, usize) {
+		let column = ((point.0 - self.min.0) / self.cell_size) as usize;
+		let row = ((point.1 - self.min.1) / self.cell_size) as usize;
+		(column.min(self.columns - 1), row.min(self.rows - 1))
+	}
+
+	/// Returns up to k points closest to the given point which are not removed.
+	fn k_nearest(&self, points: &Vec<(f64, f64)>, center: usize, k: usize, removed: &Vec<bool>) -> Vec<usize> {
+		let (column, row) = self.cell(points[center]);
+		let (column, row) = (column as isize, row as isize);
+		let mut found: Vec<(f64, usize)> = Vec::new();
+
+		for ring in 0..(max(self.columns, self.rows) as isize) {
+			// Visit the cells on the border of the square around the cell
+			for y in (row - ring)..(row + ring + 1) {
+				let step = if y == row - ring || y == row + ring { 1 } else { max(2 * ring, 1) };
+				let mut x = column - ring;
+				while x <= column + ring {
+					if x >= 0 && y >= 0 && (x as usize) < self.columns && (y as usize) < self.rows {
+						for &index in self.cells[y as usize * self.columns + x as usize].iter() {
+							if index != center && !removed[index] {
+								let distance = (points[index].0 - points[center].0).powi(2)
+									+ (points[index].1 - points[center].1).powi(2);
+								found.push((distance, index));
+							}
+						}
+					}
+					x += step;
+				}
+			}
+
+			// All points closer than the ring are found
+			if found.len() >= k {
+				found.sort_by(|first, second| first.partial_cmp(second).unwrap());
+				if found[k - 1].0 <= (ring as f64 * self.cell_size).powi(2) {
+					break;
+				}
+			}
+		}
+
+		found.sort_by(|first, second| first.partial_cmp(second).unwrap());
+		found.into_iter().take(k).map(|(_, index)| index).collect()
+	}
+}
+
+#[test]
+fn test_isochrone() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let point = |node: usize| (graph.nodes[node].latitude, graph.nodes[node].longitude);
+
+	let isochrone = graph.compute_isochrone(0, 2000.0, Hull::Convex);
+	assert_eq!(vec![0, 1, 2], *isochrone.nodes());
+	assert_eq!(vec![point(0), point(1), point(2)], *isochrone.boundary());
+
+	// Node 2 is inside
+	let isochrone = graph.compute_isochrone(0, 3000.0, Hull::Convex);
+	assert_eq!(vec![0, 1, 2, 3, 4], *isochrone.nodes());
+	assert_eq!(vec![point(0), point(1), point(4), point(3)], *isochrone.boundary());
+
+	let isochrone = graph.compute_isochrone(0, 3000.0, Hull::Concave(3));
+	let boundary = project(isochrone.boundary());
+	for &node in isochrone.nodes().iter() {
+		assert!(isochrone.boundary().contains(&point(node))
+			|| polygon_contains(&boundary, project(&vec![point(node)])[0]));
+	}
+
+	let isochrone = graph.compute_isochrone(6, 0.0, Hull::Concave(3));
+	assert_eq!(vec![6], *isochrone.nodes());
+	assert_eq!(vec![point(6)], *isochrone.boundary());
+}
+
+#[test]
+fn test_concave_hull() {
+	// A U shape with a notch from the north
+	let mut points = Vec::new();
+	for x in 0..8 {
+		for y in 0..6 {
+			if x < 3 || x > 4 || y < 2 {
+				points.push((48.0 + y as f64 * 0.001, 7.8 + x as f64 * 0.001));
+			}
+		}
+	}
+
+	let notch = (48.004, 7.8035);
+	let projected = |polygon: &Vec<(f64, f64)>, point: (f64, f64)| {
+		let mut all = polygon.clone();
+		all.push(point);
+		let all = project(&all);
+		polygon_contains(&all[..polygon.len()].to_vec(), all[polygon.len()])
+	};
+
+	let convex = convex_hull(&points);
+	assert_eq!(4, convex.len());
+	assert!(projected(&convex, notch));
+
+	let concave = concave_hull(&points, 3);
+	assert!(concave.len() > 4);
+	assert!(!projected(&concave, notch));
+	for &point in points.iter() {
+		assert!(projected(&concave, point));
+	}
+}
+
+#[test]
+fn test_isochrone_formats() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let isochrone = graph.compute_isochrone(0, 2000.0, Hull::Convex);
+
+	let mut geojson = Vec::new();
+	isochrone.write(RouteFormat::GeoJson, &mut geojson).unwrap();
+	assert_eq!("{\"type\":\"FeatureCollection\",\"features\":[{\"type\":\"Feature\",\"geometry\":{\"type\":\"Polygon\",\
+		\"coordinates\":[[[7.800000,48.000000],[7.820000,48.000000],[7.810000,48.010000],[7.800000,48.000000]]]},\
+		\"properties\":{\"max_cost\":2000.0,\"nodes\":3}}]}\n", String::from_utf8(geojson).unwrap());
+
+	let mut gpx = Vec::new();
+	isochrone.write(RouteFormat::Gpx, &mut gpx).unwrap();
+	let gpx = String::from_utf8(gpx).unwrap();
+	assert_eq!(4, gpx.matches("<trkpt").count());
+	assert!(gpx.contains(&format!(" xmlns:ex13=\"{}\">", GPX_EXTENSIONS_NAMESPACE)));
+	assert!(gpx.contains("<extensions><ex13:max_cost>2000.0</ex13:max_cost><ex13:nodes>3</ex13:nodes></extensions>"));
+
+	let mut kml = Vec::new();
+	isochrone.write(RouteFormat::Kml, &mut kml).unwrap();
+	assert!(String::from_utf8(kml).unwrap().contains("<coordinates>7.800000,48.000000 7.820000,48.000000 \
+		7.810000,48.010000 7.800000,48.000000</coordinates>"));
+
+	let mut mapbb = Vec::new();
+	isochrone.write(RouteFormat::Mapbb, &mut mapbb).unwrap();
+	assert_eq!("[map]48.0000,7.8000 48.0000,7.8200 48.0100,7.8100 48.0000,7.8000[/map]\n", String::from_utf8(mapbb).unwrap());
+}
\ No newline at end of file
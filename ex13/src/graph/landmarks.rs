@@ -0,0 +1,295 @@
+use super::Graph;
+
+use std::collections::BinaryHeap;
+use std::f64;
+use std::vec::Vec;
+
+/// Strategy to select the landmarks with.
+#[derive(Clone, Copy)]
+pub enum LandmarkSelection {
+	/// Repeatedly select the node furthest away from all landmarks so far,
+	/// starting a new sweep for nodes no landmark reaches.
+	Farthest,
+	/// Select random nodes with the given seed.
+	Random(u64)
+}
+
+/// Landmarks with the distances from and to all nodes of the graph.
+///
+/// Unreachable nodes have an infinite distance.
+pub struct Landmarks {
+	nodes: Vec<usize>,
+	from_distances: Vec<Vec<f64>>,
+	to_distances: Vec<Vec<f64>>
+}
+
+impl Landmarks {
+
+	/// Compute the distance tables of the given landmarks with the current arc costs.
+	pub fn new(graph: &Graph, nodes: Vec<usize>) -> Landmarks {
+		let from_distances = nodes.iter().map(|&node| compute_distances(graph, node, false)).collect();
+		let to_distances = nodes.iter().map(|&node| compute_distances(graph, node, true)).collect();
+
+		Landmarks {
+			nodes: nodes,
+			from_distances: from_distances,
+			to_distances: to_distances
+		}
+	}
+
+	/// Select the given number of landmarks and compute their distance tables.
+	pub fn select(graph: &Graph, count: usize, selection: LandmarkSelection) -> Landmarks {
+		let node_count = graph.num_nodes();
+		let count = ::std::cmp::min(count, node_count);
+
+		let nodes = match selection {
+			LandmarkSelection::Random(seed) => {
+				// Xorshift, the state must never be zero
+				let mut state = seed | 1;
+				let mut nodes = Vec::with_capacity(count);
+
+				while nodes.len() < count {
+					state ^= state << 13;
+					state ^= state >> 7;
+					state ^= state << 17;
+
+					let node = (state % node_count as u64) as usize;
+					if !nodes.contains(&node) {
+						nodes.push(node);
+					}
+				}
+
+				nodes
+			},
+			LandmarkSelection::Farthest => {
+				let mut nodes = Vec::with_capacity(count);
+				let mut min_distances = vec![f64::INFINITY; node_count];
+				// Nodes reached by a landmark or by a sweep
+				let mut covered = vec![false; node_count];
+
+				while nodes.len() < count {
+					// Sweep from a node not covered yet to find a landmark for its part of the graph
+					let furthest = match covered.iter().position(|&covered| !covered) {
+						Some(start_node) => {
+							let distances = compute_distances(graph, start_node, false);
+							for (covered, distance) in covered.iter_mut().zip(distances.iter()) {
+								*covered |= distance.is_finite();
+							}
+							furthest_node(&distances, &nodes)
+						},
+						None => furthest_node(&min_distances, &nodes)
+					};
+
+					let node = match furthest {
+						Some(node) => node,
+						None => { break; }
+					};
+
+					let distances = compute_distances(graph, node, false);
+					for ((min_distance, covered), distance) in min_distances.iter_mut().zip(covered.iter_mut()).zip(distances.into_iter()) {
+						if distance < *min_distance {
+							*min_distance = distance;
+						}
+						*covered |= distance.is_finite();
+					}
+
+					nodes.push(node);
+				}
+
+				nodes
+			}
+		};
+
+		Landmarks::new(graph, nodes)
+	}
+
+	/// Returns the landmark nodes.
+	pub fn nodes(&self) -> &Vec<usize> {
+		&self.nodes
+	}
+
+	/// Returns a lower bound of the distance from the node to the end node.
+	///
+	/// Uses the triangle inequality with the distances from and to all
+	/// landmarks. An infinite bound means the end node is not reachable.
+	fn lower_bound(&self, node: usize, end_node: usize) -> f64 {
+		let mut bound = 0.0;
+
+		for (from, to) in self.from_distances.iter().zip(self.to_distances.iter()) {
+			// d(l, e) - d(l, n) <= d(n, e) and d(n, l) - d(e, l) <= d(n, e)
+			for &estimate in [from[end_node] - from[node], to[node] - to[end_node]].iter() {
+				if estimate > bound {
+					bound = estimate;
+				}
+			}
+		}
+
+		return bound;
+	}
+}
+
+/// Returns the node with the largest finite distance which is not excluded.
+fn furthest_node(distances: &Vec<f64>, excluded: &Vec<usize>) -> Option<usize> {
+	let mut furthest: Option<(f64, usize)> = None;
+
+	for (node, &distance) in distances.iter().enumerate() {
+		if distance.is_infinite() || excluded.contains(&node) {
+			continue;
+		}
+
+		match furthest {
+			Some((max_distance, _)) if max_distance >= distance => { },
+			_ => { furthest = Some((distance, node)); }
+		}
+	}
+
+	return furthest.map(|(_, node)| node);
+}
+
+/// Compute the distances from (or with backward set: to) the start node to all nodes.
+fn compute_distances(graph: &Graph, start_node: usize, backward: bool) -> Vec<f64> {
+	let mut distances = vec![f64::INFINITY; graph.num_nodes()];
+	let mut settled = vec![false; graph.num_nodes()];
+
+	let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+	distances[start_node] = 0.0;
+	active_nodes.push((0, start_node));
+
+	while let Some((_, node)) = active_nodes.pop() {
+		if settled[node] {
+			continue;
+		}
+
+		settled[node] = true;
+
+		let arcs = if backward { graph.reverse_arcs(node) } else { graph.arcs(node) };
+		for index in arcs {
+			let (next_node, arc) = match backward {
+				false => (graph.arc_heads[index], index),
+				true => (graph.reverse_arc_tails[index], graph.reverse_arc_ids[index])
+			};
+			let next_distance = distances[node] + graph.arc_costs[arc];
+
+			if settled[next_node] || distances[next_node] <= next_distance {
+				continue;
+			}
+
+			distances[next_node] = next_distance;
+
+			// Use negative numbers since we only have a max-heap
+			active_nodes.push((-(next_distance * 1000.0) as i64, next_node));
+		}
+	}
+
+	return distances;
+}
+
+impl Graph {
+
+	/// Select landmarks for compute_shortest_path_alt.
+	///
+	/// The distance tables are recomputed for the selected landmarks
+	/// whenever the arc costs change.
+	pub fn select_landmarks(&mut self, count: usize, selection: LandmarkSelection) {
+		self.landmarks = Some(Box::new(Landmarks::select(self, count, selection)));
+	}
+
+	/// Returns the selected landmark nodes.
+	pub fn landmark_nodes(&self) -> Vec<usize> {
+		match self.landmarks {
+			Some(ref landmarks) => landmarks.nodes().clone(),
+			None => Vec::new()
+		}
+	}
+
+	/// Compute the shortest path between two nodes with A*, landmarks and the triangle inequality.
+	///
+	/// Without selected landmarks this is the same as compute_shortest_path_dijkstra.
+	/// The result is the same as for compute_shortest_path_astar.
+	pub fn compute_shortest_path_alt(&self, start_node: usize, end_node: usize,
+			max_speed: u64) -> Option<(f64, f64, Box<Vec<usize>>, usize)> {
+		match self.landmarks {
+			Some(ref landmarks) => {
+				self.compute_shortest_path_goal_directed(start_node, end_node, max_speed,
+					|node| landmarks.lower_bound(node, end_node))
+			},
+			None => {
+				self.compute_shortest_path_dijkstra(start_node, end_node, max_speed)
+			}
+		}
+	}
+}
+
+#[test]
+fn test_landmarks_select() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let landmarks = Landmarks::select(&graph, 2, LandmarkSelection::Farthest);
+	assert_eq!(2, landmarks.nodes().len());
+	assert!(landmarks.nodes()[0] != landmarks.nodes()[1]);
+
+	let landmarks = Landmarks::select(&graph, 20, LandmarkSelection::Random(42));
+	assert_eq!(graph.num_nodes(), landmarks.nodes().len());
+
+	assert!(Landmarks::select(&Graph::new(), 2, LandmarkSelection::Farthest).nodes().is_empty());
+}
+
+#[test]
+fn test_landmarks_components() {
+	use super::LoadMode;
+
+	let text = "4\n2\n0 48.0 7.8\n1 48.0 7.81\n2 48.1 7.8\n3 48.1 7.81\n0 1 700 50\n2 3 700 50\n";
+	let graph = Graph::read_graph_from_reader(text.as_bytes(), LoadMode::Undirected, |_| { }).unwrap();
+
+	// Each component gets a landmark and with it useful lower bounds
+	let landmarks = Landmarks::select(&graph, 2, LandmarkSelection::Farthest);
+	assert_eq!(&vec![1, 3], landmarks.nodes());
+	assert_eq!(700.0, landmarks.lower_bound(0, 1));
+	assert_eq!(700.0, landmarks.lower_bound(2, 3));
+}
+
+#[test]
+fn test_shortest_alt() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	graph.select_landmarks(2, LandmarkSelection::Farthest);
+
+	for &max_speed in [0, 130].iter() {
+		if max_speed != 0 {
+			graph.set_arc_costs_to_travel_time(max_speed);
+		}
+
+		for start in 0..graph.num_nodes() {
+			for end in 0..graph.num_nodes() {
+				let dijkstra = graph.compute_shortest_path_dijkstra(start, end, 130);
+				let alt = graph.compute_shortest_path_alt(start, end, 130);
+
+				assert_eq!(dijkstra.is_some(), alt.is_some());
+				if let (Some(dijkstra), Some(alt)) = (dijkstra, alt) {
+					if max_speed == 0 {
+						assert_eq!(dijkstra.0, alt.0);
+					} else {
+						assert_eq!((dijkstra.1 * 1000.0).round(), (alt.1 * 1000.0).round());
+					}
+					assert!(alt.3 <= dijkstra.3);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn test_landmarks_metric() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	graph.select_landmarks(1, LandmarkSelection::Random(7));
+	let nodes = graph.landmark_nodes();
+
+	let distance_bound = graph.landmarks.as_ref().unwrap().lower_bound(0, 5);
+	graph.set_arc_costs_to_travel_time(130);
+	let time_bound = graph.landmarks.as_ref().unwrap().lower_bound(0, 5);
+
+	// Same landmarks, but the tables follow the metric
+	assert_eq!(nodes, graph.landmark_nodes());
+	assert!(distance_bound <= 3650.0);
+	assert!(time_bound <= 133.2);
+	assert!(time_bound < distance_bound);
+}
\ No newline at end of file
@@ -0,0 +1,232 @@
+use super::{Graph, ShortestPaths};
+
+use std::cmp::{max, min};
+use std::collections::BinaryHeap;
+use std::f64;
+use std::io::{Result as IOResult, Write};
+use std::thread;
+use std::vec::Vec;
+
+/// Distances and travel times between sources and targets.
+///
+/// The values are stored row by row with a row for each source.
+pub struct DistanceMatrix {
+	sources: Vec<usize>,
+	targets: Vec<usize>,
+	/// Costs of the paths, only used to compare paths while filling the matrix.
+	costs: Vec<f64>,
+	distances: Vec<f64>,
+	times: Vec<f64>
+}
+
+impl DistanceMatrix {
+
+	/// Create a matrix where no target is reachable.
+	pub(super) fn new(sources: &Vec<usize>, targets: &Vec<usize>) -> DistanceMatrix {
+		let count = sources.len() * targets.len();
+
+		DistanceMatrix {
+			sources: sources.clone(),
+			targets: targets.clone(),
+			costs: vec![f64::INFINITY; count],
+			distances: vec![f64::INFINITY; count],
+			times: vec![f64::INFINITY; count]
+		}
+	}
+
+	/// Set the path between the source and target index if it is cheaper than the known one.
+	pub(super) fn update(&mut self, source_index: usize, target_index: usize, costs: f64, distance: f64, time: f64) {
+		let index = source_index * self.targets.len() + target_index;
+		if costs < self.costs[index] {
+			self.costs[index] = costs;
+			self.distances[index] = distance;
+			self.times[index] = time;
+		}
+	}
+
+	/// Returns the source nodes of the rows.
+	pub fn sources(&self) -> &Vec<usize> {
+		&self.sources
+	}
+
+	/// Returns the target nodes of the columns.
+	pub fn targets(&self) -> &Vec<usize> {
+		&self.targets
+	}
+
+	/// Returns the distance in meters from the source to the target with the
+	/// given indices, or None if the target is not reachable.
+	pub fn distance(&self, source_index: usize, target_index: usize) -> Option<f64> {
+		let distance = self.distances[source_index * self.targets.len() + target_index];
+		match distance.is_finite() {
+			true => Some(distance),
+			false => None
+		}
+	}
+
+	/// Returns the travel time in seconds from the source to the target with
+	/// the given indices, or None if the target is not reachable.
+	pub fn time(&self, source_index: usize, target_index: usize) -> Option<f64> {
+		let time = self.times[source_index * self.targets.len() + target_index];
+		match time.is_finite() {
+			true => Some(time),
+			false => None
+		}
+	}
+
+	/// Write a line with source, target, distance and travel time for every pair.
+	///
+	/// The nodes are given by their ids, unreachable pairs have empty values.
+	pub fn write_csv<W: Write>(&self, graph: &Graph, out: &mut W) -> IOResult<()> {
+		try!(write!(out, "source,target,distance,time\n"));
+
+		for (source_index, &source) in self.sources.iter().enumerate() {
+			for (target_index, &target) in self.targets.iter().enumerate() {
+				try!(write!(out, "{},{},", graph.nodes[source].id, graph.nodes[target].id));
+				match (self.distance(source_index, target_index), self.time(source_index, target_index)) {
+					(Some(distance), Some(time)) => { try!(write!(out, "{},{:.1}\n", distance, time)); },
+					_ => { try!(write!(out, ",\n")); }
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl Graph {
+
+	/// Compute the distances and travel times between all sources and targets.
+	///
+	/// Runs a Dijkstra search per source, which stops once all targets are
+	/// settled. The sources are split evenly among the given number of threads.
+	pub fn compute_distance_matrix(&self, sources: &Vec<usize>, targets: &Vec<usize>, max_speed: u64,
+			threads: usize) -> DistanceMatrix {
+		let mut matrix = DistanceMatrix::new(sources, targets);
+		if sources.is_empty() {
+			return matrix;
+		}
+
+		let threads = max(min(threads, sources.len()), 1);
+		let chunk_size = (sources.len() + threads - 1) / threads;
+
+		let rows: Vec<Vec<Option<(f64, f64, f64)>>> = thread::scope(|scope| {
+			let handles: Vec<_> = sources.chunks(chunk_size).map(|chunk| {
+				scope.spawn(move || {
+					chunk.iter()
+						.flat_map(|&source| self.compute_one_to_many(source, targets, max_speed))
+						.collect::<Vec<Option<(f64, f64, f64)>>>()
+				})
+			}).collect();
+
+			handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+		});
+
+		for (index, path) in rows.into_iter().flat_map(|row| row.into_iter()).enumerate() {
+			if let Some((costs, distance, time)) = path {
+				matrix.update(index / targets.len(), index % targets.len(), costs, distance, time);
+			}
+		}
+
+		return matrix;
+	}
+
+	/// Returns the costs, distance and travel time of the paths from the source to the targets.
+	///
+	/// Runs Dijkstra's algorithm until all targets are settled.
+	fn compute_one_to_many(&self, source: usize, targets: &Vec<usize>, max_speed: u64) -> Vec<Option<(f64, f64, f64)>> {
+		let mut paths = ShortestPaths::new(self.num_nodes(), source);
+		let mut remaining = vec![false; self.num_nodes()];
+		for &target in targets.iter() {
+			remaining[target] = true;
+		}
+		let mut num_remaining = remaining.iter().filter(|&&remaining| remaining).count();
+
+		let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+		active_nodes.push((0, source));
+
+		while let Some((_, node)) = active_nodes.pop() {
+			if paths.settled[node] {
+				continue;
+			}
+
+			paths.settled[node] = true;
+			if remaining[node] {
+				remaining[node] = false;
+				num_remaining -= 1;
+				if num_remaining == 0 {
+					break;
+				}
+			}
+
+			let node_distance = paths.distances[node].unwrap();
+			for arc in self.arcs(node) {
+				let head_node = self.arc_heads[arc];
+				if paths.settled[head_node] {
+					continue;
+				}
+
+				let next_distance = node_distance + self.arc_costs[arc];
+				match paths.distances[head_node] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+
+				paths.distances[head_node] = Some(next_distance);
+				paths.traceback_arcs[head_node] = Some((node, arc));
+
+				// Use negative numbers since we only have a max-heap
+				active_nodes.push((-(next_distance * 1000.0) as i64, head_node));
+			}
+		}
+
+		targets.iter()
+			.map(|&target| {
+				if !paths.settled[target] {
+					return None;
+				}
+
+				let (distance, time, _) = self.trace_path(&paths.traceback_arcs, target, max_speed);
+				Some((paths.distances[target].unwrap(), distance, time))
+			})
+			.collect()
+	}
+}
+
+#[test]
+fn test_distance_matrix() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let sources = vec![0, 5, 6];
+	let targets = vec![5, 0, 3, 6];
+
+	for &threads in [1, 2, 8].iter() {
+		let matrix = graph.compute_distance_matrix(&sources, &targets, 130, threads);
+		assert_eq!(Some(3650.0), matrix.distance(0, 0));
+		assert_eq!(Some(0.0), matrix.distance(0, 1));
+		assert_eq!(None, matrix.distance(0, 3));
+		assert_eq!(Some(0.0), matrix.time(1, 0));
+		assert_eq!(Some(5000.0), matrix.distance(2, 0));
+		assert_eq!(Some(0.0), matrix.distance(2, 3));
+
+		for (source_index, &source) in sources.iter().enumerate() {
+			for (target_index, &target) in targets.iter().enumerate() {
+				let path = graph.compute_shortest_path_dijkstra(source, target, 130);
+				assert_eq!(path.as_ref().map(|path| path.0), matrix.distance(source_index, target_index));
+				assert_eq!(path.as_ref().map(|path| path.1), matrix.time(source_index, target_index));
+			}
+		}
+	}
+
+	// Fastest paths
+	graph.set_arc_costs_to_travel_time(130);
+	let matrix = graph.compute_distance_matrix(&sources, &targets, 130, 2);
+	assert_eq!(Some(3700.0), matrix.distance(0, 0));
+
+	let mut csv = Vec::new();
+	matrix.write_csv(&graph, &mut csv).unwrap();
+	let csv = String::from_utf8(csv).unwrap();
+	assert!(csv.starts_with("source,target,distance,time\n0,5,3700,133.2\n0,0,0,0.0\n"));
+	assert!(csv.contains("\n0,6,,\n"));
+
+	assert_eq!(None, graph.compute_distance_matrix(&Vec::new(), &targets, 130, 2).sources().first());
+}
\ No newline at end of file
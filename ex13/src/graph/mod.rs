@@ -0,0 +1,1249 @@
+extern crate zip;
+
+mod algorithms;
+mod alternatives;
+mod cache;
+mod components;
+mod contraction;
+mod export;
+mod flow;
+mod input;
+mod isochrone;
+mod landmarks;
+mod matrix;
+mod osm;
+mod pareto;
+mod pbf;
+mod spanning;
+mod spatial;
+mod statistics;
+mod subgraph;
+mod traffic;
+mod turns;
+mod validation;
+
+pub use self::algorithms::{bellman_ford, breadth_first_search, depth_first_search, floyd_warshall, johnson,
+	topological_sort, AdjacencyList, WeightedGraph};
+pub use self::components::Components;
+pub use self::contraction::ContractionHierarchy;
+pub use self::export::{Route, RouteFormat};
+pub use self::flow::Capacity;
+pub use self::flow::MaxFlow;
+pub use self::input::Progress;
+pub use self::input::InputFormat;
+pub use self::isochrone::Hull;
+pub use self::isochrone::Isochrone;
+pub use self::landmarks::LandmarkSelection;
+pub use self::matrix::DistanceMatrix;
+pub use self::spanning::SpanningTree;
+pub use self::spatial::SpatialIndex;
+pub use self::statistics::Statistics;
+pub use self::subgraph::NodeMapping;
+pub use self::traffic::{parse_time_of_day, Traffic};
+pub use self::traffic::TravelTimeProfile;
+pub use self::turns::{TurnCosts, TurnRestrictions};
+pub use self::validation::Issue;
+pub use self::validation::Diagnostic;
+
+use self::input::PROGRESS_INTERVAL;
+use self::landmarks::Landmarks;
+
+use self::zip::ZipWriter;
+use self::zip::result::ZipError;
+use self::zip::write::FileOptions;
+
+use std::collections::{BinaryHeap, HashSet};
+use std::cmp::{max, min};
+use std::error::Error as StdError;
+use std::f64;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Error as IOError, Result as IOResult, Write};
+use std::num::{ParseFloatError, ParseIntError};
+use std::ops::Range;
+use std::path::Path;
+use std::result::Result;
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Debug)]
+pub enum Error {
+	FormatError { message: String },
+	IoError(IOError),
+	ParseIntError(ParseIntError),
+	ParseFloatError(ParseFloatError),
+	ZipError(ZipError)
+}
+
+impl From<ParseIntError> for Error {
+    fn from(err: ParseIntError) -> Error {
+        Error::ParseIntError(err)
+    }
+}
+
+impl From<ParseFloatError> for Error {
+    fn from(err: ParseFloatError) -> Error {
+        Error::ParseFloatError(err)
+    }
+}
+
+impl From<IOError> for Error {
+    fn from(err: IOError) -> Error {
+        Error::IoError(err)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(err: ZipError) -> Error {
+        Error::ZipError(err)
+    }
+}
+
+impl<'a> From<&'a str> for Error {
+	fn from(err: &str) -> Error {
+		Error::FormatError {
+			message: err.to_owned()
+		}
+	}
+}
+
+impl From<String> for Error {
+	fn from(err: String) -> Error {
+		Error::FormatError {
+			message: err
+		}
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match *self {
+			Error::IoError(ref err) => write!(f, "IO Error: {}", err),
+			Error::ParseIntError(ref err) => write!(f, "Parse Error: {}", err),
+			Error::ParseFloatError(ref err) => write!(f, "Parse Error: {}", err),
+			Error::ZipError(ref err) => write!(f, "Zip Error: {}", err),
+			Error::FormatError { ref message } => write!(f, "Format error: {}", message)
+		}
+	}
+}
+
+impl StdError for Error {
+	fn description(&self) -> &str {
+		match *self {
+			Error::IoError(ref err) => err.description(),
+			Error::ParseIntError(ref err) => err.description(),
+			Error::ParseFloatError(ref err) => err.description(),
+			Error::ZipError(ref err) => err.description(),
+			Error::FormatError { ref message } => &message
+		}
+	}
+	
+	fn cause(&self) -> Option<&StdError> {
+		match *self {
+			Error::IoError(ref err) => Some(err),
+			Error::ParseIntError(ref err) => Some(err),
+			Error::ParseFloatError(ref err) => Some(err),
+			Error::ZipError(ref err) => Some(err),
+			Error::FormatError { .. } => None
+		}
+	}
+}
+
+const MAPBB_NODE_DIVIDER: u64 = 15;
+
+/// Mean earth radius in meters.
+const EARTH_RADIUS: f64 = 6371000.0;
+
+struct Node {
+	id: usize,
+	latitude: f64,
+	longitude: f64
+}
+
+impl Node {
+	/// Compute the great-circle distance in meters to the other node.
+	fn great_circle_distance(&self, other: &Node) -> f64 {
+		let (latitude1, latitude2) = (self.latitude.to_radians(), other.latitude.to_radians());
+		let delta_latitude = latitude2 - latitude1;
+		let delta_longitude = (other.longitude - self.longitude).to_radians();
+		
+		// Haversine formula
+		let a = (delta_latitude / 2.0).sin().powi(2)
+			+ latitude1.cos() * latitude2.cos() * (delta_longitude / 2.0).sin().powi(2);
+		2.0 * EARTH_RADIUS * a.sqrt().asin()
+	}
+}
+
+/// How the arcs of a graph file become arcs of the graph.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LoadMode {
+	/// Each arc from its tail to its head node, as given in the file.
+	Directed,
+	/// Each arc in both directions, unless the file already contains the opposite arc.
+	Undirected,
+	/// Each arc from its head to its tail node, to search backwards from a target.
+	Reverse
+}
+
+/// The metric the arc costs are currently set to.
+enum Metric {
+	Distance,
+	/// Travel time with the fastest speed any arc can be traveled with.
+	TravelTime(u64)
+}
+
+/// A road graph with its arcs in compressed sparse row layout.
+///
+/// The outgoing arcs of a node are stored consecutively and identified
+/// by their index (arc id) in the arc arrays. They range from the offset
+/// of the node up to the offset of the next node. The incoming arcs are
+/// stored the same way as tail node and arc id.
+pub struct Graph {
+	metric: Metric,
+	landmarks: Option<Box<Landmarks>>,
+	nodes: Box<Vec<Node>>,
+	arc_offsets: Vec<usize>,
+	arc_heads: Vec<usize>,
+	arc_distances: Vec<u64>,
+	arc_max_speeds: Vec<u64>,
+	arc_costs: Vec<f64>,
+	reverse_arc_offsets: Vec<usize>,
+	reverse_arc_tails: Vec<usize>,
+	reverse_arc_ids: Vec<usize>
+}
+
+/// State and result of a shortest path search from a single start node.
+///
+/// The search state is kept apart from the graph, so that multiple
+/// searches can run on the same graph and their results can be kept
+/// side by side.
+pub struct ShortestPaths {
+	start_node: usize,
+	distances: Vec<Option<f64>>,
+	settled: Vec<bool>,
+	/// Tail node and id of the arc used to reach a node.
+	traceback_arcs: Vec<Option<(usize, usize)>>,
+	num_settled: usize
+}
+
+impl ShortestPaths {
+	
+	fn new(node_count: usize, start_node: usize) -> ShortestPaths {
+		let mut distances = vec![None; node_count];
+		distances[start_node] = Some(0.0);
+		
+		ShortestPaths {
+			start_node: start_node,
+			distances: distances,
+			settled: vec![false; node_count],
+			traceback_arcs: vec![None; node_count],
+			num_settled: 0
+		}
+	}
+	
+	/// Returns the start node of the search.
+	pub fn start_node(&self) -> usize {
+		self.start_node
+	}
+	
+	/// Returns the costs of the shortest path to the node, or None if it
+	/// was not reached by the search.
+	pub fn distance(&self, node: usize) -> Option<f64> {
+		self.distances[node]
+	}
+	
+	/// Returns if the shortest path to the node is final.
+	pub fn is_settled(&self, node: usize) -> bool {
+		self.settled[node]
+	}
+	
+	/// Returns the costs of the shortest paths to all nodes.
+	pub fn distances(&self) -> &Vec<Option<f64>> {
+		&self.distances
+	}
+	
+	/// Returns the nodes of the path from the start node to the node, or
+	/// None if the node was not reached by the search.
+	pub fn path(&self, node: usize) -> Option<Box<Vec<usize>>> {
+		if self.distances[node].is_none() {
+			return None;
+		}
+		
+		let mut path = Box::new(vec![node]);
+		let mut current = node;
+		while let Some((tail_node, _)) = self.traceback_arcs[current] {
+			path.push(tail_node);
+			current = tail_node;
+		}
+		path.reverse();
+		
+		return Some(path);
+	}
+	
+	/// Returns the number of settled nodes.
+	pub fn num_settled(&self) -> usize {
+		self.num_settled
+	}
+	
+	/// Returns the furthes node from the start node
+	pub fn get_furthest_node(&self) -> (f64, usize) {
+		let mut max_dist = (0.0, 0);
+		
+		for (node, distance) in self.distances.iter().enumerate() {
+			match *distance {
+				None => { },
+				Some(dist) => {
+					if max_dist.0 < dist {
+						max_dist = (dist, node);
+					}
+				}
+			}
+		}
+		
+		return max_dist;
+	}
+}
+
+impl Graph {
+	
+	/// Read nodes and arcs in text format.
+	///
+	/// The arcs are collected as tail node, head node, distance and max speed.
+	/// The progress is reported every PROGRESS_INTERVAL lines and at the end.
+	fn read_lines<R: BufRead, P: FnMut(&Progress)>(&mut self, buf: R, arcs: &mut Vec<(usize, usize, u64, u64)>,
+			progress: &mut P) -> Result<(), Error> {
+		let mut line_number = 0;
+		let mut total_line_number = 0;
+		
+		let mut node_count = 0;
+		let mut arc_count = 0;
+		
+		for line_res in buf.lines() {
+			let line = try!(line_res);
+			let line = line.trim();
+			
+			total_line_number += 1;
+			
+			if total_line_number % PROGRESS_INTERVAL == 0 {
+				progress(&Progress { lines: total_line_number, nodes: self.nodes.len(), arcs: arcs.len(),
+					total_nodes: node_count, total_arcs: arc_count });
+			}
+			
+			if line.starts_with("#") || line.is_empty() {
+				continue;
+			}
+			
+			line_number += 1;
+			
+			let parts: Vec<&str> = line.split(' ').collect();
+			if parts.len() < 1 {
+				return Err(Error::from(format!("Invalid graph file! (general, line {})", total_line_number)));
+			}
+			
+			match line_number {
+				1 => {
+					node_count = try!(parts[0].parse::<usize>());
+					self.nodes.reserve_exact(node_count);
+				},
+				2 => {
+					arc_count = try!(parts[0].parse::<usize>());
+					arcs.reserve_exact(arc_count);
+				},
+				_ => {
+					if line_number < node_count + 3 {
+						if parts.len() != 3 {
+							return Err(Error::from(format!("Invalid graph file! (Invalid node, line {})", total_line_number)));
+						}
+						
+						let node_id = try!(parts[0].parse::<usize>());
+						let latitude = try!(parts[1].parse::<f64>());
+						let longitude = try!(parts[2].parse::<f64>());
+						
+						self.nodes.push(Node {
+							id: node_id,
+							latitude: latitude,
+							longitude: longitude
+						});
+ 					} else if line_number < node_count + arc_count + 3 {
+						if parts.len() != 4 {
+							return Err(Error::from(format!("Invalid graph file! (Invalid arc, line {})", total_line_number)));
+						}
+						
+						let tail_node = try!(parts[0].parse::<usize>());
+						let head_node = try!(parts[1].parse::<usize>());
+						
+						let distance = try!(parts[2].parse::<u64>());
+						let max_speed = try!(parts[3].parse::<u64>());
+						
+						if tail_node >= self.nodes.len() || head_node >= self.nodes.len() {
+							return Err(Error::from(format!("Invalid graph file! (Invalid arc node, line {})", total_line_number)));
+						}
+						
+						arcs.push((tail_node, head_node, distance, max_speed));
+ 					} else {
+	 					return Err(Error::from(format!("Invalid graph file! (Additional lines, line {})", total_line_number)));
+ 					}
+				}
+			}
+		}
+		
+		progress(&Progress { lines: total_line_number, nodes: self.nodes.len(), arcs: arcs.len(),
+			total_nodes: node_count, total_arcs: arc_count });
+		Ok(())
+	}
+	
+	/// Create a graph without nodes and arcs.
+	fn new() -> Graph {
+		Graph {
+			metric: Metric::Distance,
+			landmarks: None,
+			nodes: Box::new(Vec::new()),
+			arc_offsets: vec![0],
+			arc_heads: Vec::new(),
+			arc_distances: Vec::new(),
+			arc_max_speeds: Vec::new(),
+			arc_costs: Vec::new(),
+			reverse_arc_offsets: vec![0],
+			reverse_arc_tails: Vec::new(),
+			reverse_arc_ids: Vec::new()
+		}
+	}
+	
+	/// Read the graph in text format with the arcs as given.
+	///
+	/// The file may be a zip archive, a gzip stream or plain text, "-" reads
+	/// from stdin.
+	pub fn read_graph_from_file(name: &str) -> Result<Graph, Error> {
+		Graph::read_graph_from_file_with_mode(name, LoadMode::Directed)
+	}
+	
+	/// Read the graph in text format with the arcs turned as given by the mode.
+	///
+	/// Independent of the mode, the incoming arcs of each node are available
+	/// for backward searches as well.
+	pub fn read_graph_from_file_with_mode(name: &str, mode: LoadMode) -> Result<Graph, Error> {
+		Graph::read_graph_from_file_with_progress(name, mode, |_| { })
+	}
+	
+	/// Read the graph in text format and report the progress while reading.
+	///
+	/// The format is given by the extension of the file or by its magic bytes,
+	/// zip archives must contain a single file.
+	pub fn read_graph_from_file_with_progress<P: FnMut(&Progress)>(name: &str, mode: LoadMode,
+			progress: P) -> Result<Graph, Error> {
+		input::with_graph_text(name, |buf| Graph::read_graph_from_text(buf, mode, progress))
+	}
+	
+	/// Read the graph from the reader and report the progress while reading.
+	///
+	/// The text may be compressed as gzip stream or zip archive, which is
+	/// detected by the magic bytes.
+	pub fn read_graph_from_reader<R: BufRead, P: FnMut(&Progress)>(buf: R, mode: LoadMode,
+			progress: P) -> Result<Graph, Error> {
+		input::with_text(buf, None, |buf| Graph::read_graph_from_text(buf, mode, progress))
+	}
+	
+	fn read_graph_from_text<R: BufRead, P: FnMut(&Progress)>(buf: R, mode: LoadMode,
+			mut progress: P) -> Result<Graph, Error> {
+		let mut graph = Graph::new();
+		let mut arcs = Vec::new();
+		
+		try!(graph.read_lines(buf, &mut arcs, &mut progress));
+		
+		graph.build_arcs(Graph::orient_arcs(arcs, mode));
+		Ok(graph)
+	}
+	
+	/// Turn the arcs read from a file as given by the mode.
+	fn orient_arcs(arcs: Vec<(usize, usize, u64, u64)>, mode: LoadMode) -> Vec<(usize, usize, u64, u64)> {
+		match mode {
+			LoadMode::Directed => arcs,
+			LoadMode::Reverse => {
+				arcs.into_iter()
+					.map(|(tail_node, head_node, distance, max_speed)| (head_node, tail_node, distance, max_speed))
+					.collect()
+			},
+			LoadMode::Undirected => {
+				// Roads in both directions are usually given by an arc in each direction
+				let existing: HashSet<(usize, usize)> = arcs.iter()
+					.map(|&(tail_node, head_node, _, _)| (tail_node, head_node))
+					.collect();
+				
+				let mut undirected = Vec::with_capacity(2 * arcs.len());
+				for &(tail_node, head_node, distance, max_speed) in arcs.iter() {
+					undirected.push((tail_node, head_node, distance, max_speed));
+					if !existing.contains(&(head_node, tail_node)) {
+						undirected.push((head_node, tail_node, distance, max_speed));
+					}
+				}
+				
+				undirected
+			}
+		}
+	}
+	
+	/// Write the graph in text format.
+	///
+	/// Files ending with ".zip" are written as zip archive with a single entry.
+	pub fn write_graph_to_file(&self, name: &str) -> Result<(), Error> {
+		let file = try!(File::create(name));
+		
+		if name.ends_with(".zip") {
+			let entry_name = Path::new(name).file_stem().and_then(|stem| stem.to_str()).unwrap_or("graph");
+			
+			let mut archive = ZipWriter::new(file);
+			try!(archive.start_file(format!("{}.txt", entry_name), FileOptions::default()));
+			try!(self.write_lines(&mut BufWriter::new(&mut archive)));
+			try!(archive.finish());
+		} else {
+			try!(self.write_lines(&mut BufWriter::new(file)));
+		}
+		
+		Ok(())
+	}
+	
+	/// Write nodes and arcs in text format.
+	fn write_lines<W: Write>(&self, buf: &mut W) -> IOResult<()> {
+		try!(write!(buf, "# Number of nodes.\n{}\n", self.num_nodes()));
+		try!(write!(buf, "# Number of arcs.\n{}\n", self.num_arcs()));
+		
+		try!(write!(buf, "# Node information.\n"));
+		for node in self.nodes.iter() {
+			try!(write!(buf, "{} {} {}\n", node.id, node.latitude, node.longitude));
+		}
+		
+		try!(write!(buf, "# Arc information.\n"));
+		for tail_node in 0..self.num_nodes() {
+			for arc in self.arcs(tail_node) {
+				try!(write!(buf, "{} {} {} {}\n", tail_node, self.arc_heads[arc],
+					self.arc_distances[arc], self.arc_max_speeds[arc]));
+			}
+		}
+		
+		buf.flush()
+	}
+	
+	/// Sort the arcs given as tail node, head node, distance and max speed
+	/// into the arc arrays.
+	///
+	/// Arcs with the same tail node keep their order.
+	fn build_arcs(&mut self, arcs: Vec<(usize, usize, u64, u64)>) {
+		let node_count = self.num_nodes();
+		let arc_count = arcs.len();
+		
+		// Counting sort by tail node
+		let mut offsets = vec![0; node_count + 1];
+		for &(tail_node, _, _, _) in arcs.iter() {
+			offsets[tail_node + 1] += 1;
+		}
+		for node in 0..node_count {
+			offsets[node + 1] += offsets[node];
+		}
+		
+		let mut next_arcs = offsets.clone();
+		self.arc_heads = vec![0; arc_count];
+		self.arc_distances = vec![0; arc_count];
+		self.arc_max_speeds = vec![0; arc_count];
+		self.arc_costs = vec![0.0; arc_count];
+		
+		for (tail_node, head_node, distance, max_speed) in arcs.into_iter() {
+			let arc = next_arcs[tail_node];
+			next_arcs[tail_node] += 1;
+			
+			self.arc_heads[arc] = head_node;
+			self.arc_distances[arc] = distance;
+			self.arc_max_speeds[arc] = max_speed;
+			self.arc_costs[arc] = distance as f64;
+		}
+		
+		self.arc_offsets = offsets;
+		self.build_reverse_arcs();
+	}
+	
+	/// Sort the arcs by head node into the reverse arc arrays.
+	fn build_reverse_arcs(&mut self) {
+		let node_count = self.num_nodes();
+		let arc_count = self.num_arcs();
+		
+		// Counting sort by head node for the incoming arcs
+		let mut offsets = vec![0; node_count + 1];
+		for &head_node in self.arc_heads.iter() {
+			offsets[head_node + 1] += 1;
+		}
+		for node in 0..node_count {
+			offsets[node + 1] += offsets[node];
+		}
+		
+		let mut next_arcs = offsets.clone();
+		self.reverse_arc_tails = vec![0; arc_count];
+		self.reverse_arc_ids = vec![0; arc_count];
+		
+		for tail_node in 0..node_count {
+			for arc in self.arcs(tail_node) {
+				let head_node = self.arc_heads[arc];
+				let position = next_arcs[head_node];
+				next_arcs[head_node] += 1;
+				
+				self.reverse_arc_tails[position] = tail_node;
+				self.reverse_arc_ids[position] = arc;
+			}
+		}
+		
+		self.reverse_arc_offsets = offsets;
+	}
+	
+	/// Returns the ids of the outgoing arcs of the node.
+	fn arcs(&self, node: usize) -> Range<usize> {
+		self.arc_offsets[node] .. self.arc_offsets[node + 1]
+	}
+	
+	/// Returns the positions of the incoming arcs of the node
+	/// in the reverse arc arrays.
+	fn reverse_arcs(&self, node: usize) -> Range<usize> {
+		self.reverse_arc_offsets[node] .. self.reverse_arc_offsets[node + 1]
+	}
+	
+	/// Compute travel time of the arc in seconds with the given max vehicle speed.
+	fn arc_travel_time(&self, arc: usize, max_vehicle_speed: u64) -> f64 {
+		// s = v * t => t = s / v
+		// t = [s] = s / v = [m] / [m/s] = [m] / ([km/h] / 3.6)
+		(self.arc_distances[arc] as f64) * 3.6 / min(self.arc_max_speeds[arc], max_vehicle_speed) as f64
+	}
+	
+	/// Set arc costs to travel time in whole seconds.
+	pub fn set_arc_costs_to_travel_time(&mut self, max_vehicle_speed: u64) {
+		let mut fastest_speed = 0;
+		
+		for arc in 0..self.num_arcs() {
+			// Compute travel time in whole seconds
+			self.arc_costs[arc] = self.arc_travel_time(arc, max_vehicle_speed);
+			fastest_speed = max(fastest_speed, min(self.arc_max_speeds[arc], max_vehicle_speed));
+		}
+		
+		self.metric = Metric::TravelTime(fastest_speed);
+		self.update_landmarks();
+	}
+	
+	/// Set arc costs to distance.
+	pub fn set_arc_costs_to_distance(&mut self) {
+		for (costs, &distance) in self.arc_costs.iter_mut().zip(self.arc_distances.iter()) {
+			*costs = distance as f64;
+		}
+		
+		self.metric = Metric::Distance;
+		self.update_landmarks();
+	}
+	
+	/// Recompute the distance tables of the landmarks for the current arc costs.
+	fn update_landmarks(&mut self) {
+		if let Some(landmarks) = self.landmarks.take() {
+			self.landmarks = Some(Box::new(Landmarks::new(self, landmarks.nodes().clone())));
+		}
+	}
+	
+	/// Returns the number of nodes in this graph.
+	pub fn num_nodes(&self) -> usize {
+		self.nodes.len()
+	}
+	
+	/// Returns the node with the given id of the graph file.
+	pub fn node_with_id(&self, id: usize) -> Option<usize> {
+		// The ids usually are the node positions
+		if id < self.num_nodes() && self.nodes[id].id == id {
+			return Some(id);
+		}
+		
+		self.nodes.iter().position(|node| node.id == id)
+	}
+	
+	/// Return the number of arcs in this graph.
+	pub fn num_arcs(&self) -> usize {
+		self.arc_heads.len()
+	}
+	
+	/// Returns the tail and head node of the arc.
+	pub fn arc_nodes(&self, arc: usize) -> (usize, usize) {
+		(self.arc_tail(arc), self.arc_heads[arc])
+	}
+	
+	/// Compute all reachable nodes from the given start node.
+	///
+	/// The result is a tuple of the number selected nodes and an 'mark' list
+	/// where a value of '1' represents a visited node. 
+	pub fn compute_reachable_nodes(&self, node_id: usize) -> (usize, Box<Vec<u8>>) {
+		let mut marked_nodes = Box::new(vec![0_u8; self.num_nodes()]);
+		let mut num_marked = 0;
+		
+		let mut pending_nodes = Box::new(HashSet::<usize>::new());
+		pending_nodes.insert(node_id);
+		
+		while !pending_nodes.is_empty() {
+			let mut next_nodes = Box::new(HashSet::<usize>::new());
+			
+			for node in pending_nodes.drain() {
+				if marked_nodes[node] == 1 {
+					continue;
+				}
+				
+				marked_nodes[node] = 1;
+				num_marked += 1;
+				
+				for arc in self.arcs(node) {
+					if marked_nodes[self.arc_heads[arc]] == 0 {
+						next_nodes.insert(self.arc_heads[arc]);
+					}
+				}
+			}
+			
+			pending_nodes = next_nodes;
+		}
+		
+		return (num_marked, marked_nodes);
+	}
+	
+	/// Mark all nodes in the largest strongly connected component.
+	///
+	/// The result is a tuple comprising of the number of nodes in the
+	/// lcc and the list of nodes contained in it.
+	pub fn compute_lcc(&self) -> (usize, Box<Vec<usize>>){
+		let components = self.compute_strongly_connected_components();
+		
+		match components.largest() {
+			Some(component) => (components.sizes()[component], components.nodes(component)),
+			None => (0, Box::new(Vec::new()))
+		}
+	}
+	
+	/// Compute the shortest paths for a given start node.
+	///
+	/// Compute the shortest paths from the given start node
+	/// using Dijkstra's algorithm.
+	pub fn compute_shortest_paths(&self, start_node: usize) -> ShortestPaths {
+		let mut paths = ShortestPaths::new(self.num_nodes(), start_node);
+		self.run_search(&mut paths, None, f64::INFINITY, |_| 0.0);
+		
+		return paths;
+	}
+	
+	/// Compute the shortest paths from the start node up to the given costs.
+	///
+	/// Only the nodes with costs up to max_cost are settled,
+	/// the search stops at the first node with higher costs.
+	pub fn compute_shortest_paths_within(&self, start_node: usize, max_cost: f64) -> ShortestPaths {
+		let mut paths = ShortestPaths::new(self.num_nodes(), start_node);
+		self.run_search(&mut paths, None, max_cost, |_| 0.0);
+		
+		return paths;
+	}
+	
+	/// Compute the shortest path between two nodes.
+	///
+	/// Runs Dijkstra's algorithm forward from the start node and backward
+	/// from the end node over the incoming arcs until both searches meet.
+	/// The result is a tuple of the distance, the travel time with the given
+	/// max speed and the nodes on the path, or None if the end node is not
+	/// reachable. The graph itself is left untouched.
+	pub fn compute_shortest_path_bidirectional(&self, start_node: usize, end_node: usize,
+			max_speed: u64) -> Option<(f64, f64, Box<Vec<usize>>)> {
+		let node_count = self.num_nodes();
+		
+		// Index 0 holds the forward search, index 1 the backward search
+		let mut distances = [vec![None; node_count], vec![None; node_count]];
+		let mut settled = [vec![false; node_count], vec![false; node_count]];
+		let mut traceback_arcs = [vec![None; node_count], vec![None; node_count]];
+		let mut active_nodes = [BinaryHeap::<(i64, usize)>::new(), BinaryHeap::<(i64, usize)>::new()];
+		let mut settled_distance = [0.0, 0.0];
+		
+		distances[0][start_node] = Some(0.0);
+		distances[1][end_node] = Some(0.0);
+		active_nodes[0].push((0, start_node));
+		active_nodes[1].push((0, end_node));
+		
+		// Costs and meeting node of the best path found so far
+		let mut best: Option<(f64, usize)> = None;
+		if start_node == end_node {
+			best = Some((0.0, start_node));
+		}
+		
+		loop {
+			// Continue the search with the smaller tentative distance,
+			// one exhausted search already found every connection
+			let direction = match (active_nodes[0].peek(), active_nodes[1].peek()) {
+				(Some(forward), Some(backward)) => if forward.0 >= backward.0 { 0 } else { 1 },
+				_ => { break; }
+			};
+			
+			let (_, node_index) = active_nodes[direction].pop().unwrap();
+			if settled[direction][node_index] {
+				continue;
+			}
+			
+			settled[direction][node_index] = true;
+			
+			let node_distance: f64 = distances[direction][node_index].unwrap();
+			settled_distance[direction] = node_distance;
+			
+			// No shorter path can be found once both searches passed the best one
+			match best {
+				Some((costs, _)) if settled_distance[0] + settled_distance[1] >= costs => { break; },
+				_ => { }
+			}
+			
+			let arcs = match direction {
+				0 => self.arcs(node_index),
+				_ => self.reverse_arcs(node_index)
+			};
+			
+			for index in arcs {
+				let (next_node, arc) = match direction {
+					0 => (self.arc_heads[index], index),
+					_ => (self.reverse_arc_tails[index], self.reverse_arc_ids[index])
+				};
+				
+				if settled[direction][next_node] {
+					continue;
+				}
+				
+				let next_distance = node_distance + self.arc_costs[arc];
+				match distances[direction][next_node] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+				
+				distances[direction][next_node] = Some(next_distance);
+				traceback_arcs[direction][next_node] = Some((node_index, arc));
+				
+				// Use negative numbers since we only have a max-heap
+				active_nodes[direction].push((-(next_distance * 1000.0) as i64, next_node));
+				
+				// Both searches meet at the next node
+				match distances[1 - direction][next_node] {
+					Some(distance) => {
+						let costs = next_distance + distance;
+						match best {
+							Some((best_costs, _)) if best_costs <= costs => { },
+							_ => { best = Some((costs, next_node)); }
+						}
+					},
+					None => { }
+				}
+			}
+		}
+		
+		let meeting_node = match best {
+			Some((_, node)) => node,
+			None => { return None; }
+		};
+		
+		// Follow the forward search back to the start node
+		let (mut distance, mut time, mut path) = self.trace_path(&traceback_arcs[0], meeting_node, max_speed);
+		
+		// Follow the backward search to the end node
+		let mut node = meeting_node;
+		while let Some((head_node, arc)) = traceback_arcs[1][node] {
+			distance += self.arc_distances[arc] as f64;
+			time += self.arc_travel_time(arc, max_speed);
+			
+			path.push(head_node);
+			node = head_node;
+		}
+		
+		return Some((distance, time, path));
+	}
+	
+	/// Compute the shortest path between two nodes with A*.
+	///
+	/// The great-circle distance to the end node is used as lower bound
+	/// for distance costs, and the great-circle distance traveled with the
+	/// fastest possible speed for travel time costs. The result is a tuple
+	/// of the distance, the travel time with the given max speed, the nodes
+	/// on the path and the number of settled nodes.
+	pub fn compute_shortest_path_astar(&self, start_node: usize, end_node: usize,
+			max_speed: u64) -> Option<(f64, f64, Box<Vec<usize>>, usize)> {
+		let end = &self.nodes[end_node];
+		
+		match self.metric {
+			Metric::Distance => {
+				self.compute_shortest_path_goal_directed(start_node, end_node, max_speed,
+					|node| self.nodes[node].great_circle_distance(end))
+			},
+			Metric::TravelTime(0) => {
+				self.compute_shortest_path_goal_directed(start_node, end_node, max_speed, |_| 0.0)
+			},
+			Metric::TravelTime(fastest_speed) => {
+				self.compute_shortest_path_goal_directed(start_node, end_node, max_speed,
+					|node| self.nodes[node].great_circle_distance(end) * 3.6 / fastest_speed as f64)
+			}
+		}
+	}
+	
+	/// Compute the shortest path between two nodes with Dijkstra's algorithm.
+	///
+	/// In contrast to compute_shortest_paths the search stops as soon as
+	/// the end node is settled. The result is the same as for
+	/// compute_shortest_path_astar.
+	pub fn compute_shortest_path_dijkstra(&self, start_node: usize, end_node: usize,
+			max_speed: u64) -> Option<(f64, f64, Box<Vec<usize>>, usize)> {
+		self.compute_shortest_path_goal_directed(start_node, end_node, max_speed, |_| 0.0)
+	}
+	
+	/// Run Dijkstra's algorithm with the costs reduced by the given lower bound.
+	fn compute_shortest_path_goal_directed<H>(&self, start_node: usize, end_node: usize,
+			max_speed: u64, lower_bound: H) -> Option<(f64, f64, Box<Vec<usize>>, usize)>
+			where H: Fn(usize) -> f64 {
+		let mut paths = ShortestPaths::new(self.num_nodes(), start_node);
+		self.run_search(&mut paths, Some(end_node), f64::INFINITY, lower_bound);
+		
+		if !paths.settled[end_node] {
+			return None;
+		}
+		
+		let (distance, time, path) = self.trace_path(&paths.traceback_arcs, end_node, max_speed);
+		return Some((distance, time, path, paths.num_settled));
+	}
+	
+	/// Run Dijkstra's algorithm until the end node is settled.
+	///
+	/// Without end node all reachable nodes with costs up to max_cost
+	/// are settled. The keys of the nodes are increased by the given
+	/// lower bound of their remaining costs to the end node.
+	fn run_search<H>(&self, paths: &mut ShortestPaths, end_node: Option<usize>, max_cost: f64, lower_bound: H)
+			where H: Fn(usize) -> f64 {
+		let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+		active_nodes.push((-(lower_bound(paths.start_node) * 1000.0) as i64, paths.start_node));
+		
+		while let Some((_, node_index)) = active_nodes.pop() {
+			// Node was already settled
+			if paths.settled[node_index] {
+				continue;
+			}
+			
+			// All remaining nodes are too far away
+			if paths.distances[node_index].unwrap() > max_cost {
+				return;
+			}
+			
+			// Settle active node
+			paths.settled[node_index] = true;
+			paths.num_settled += 1;
+			
+			if Some(node_index) == end_node {
+				return;
+			}
+			
+			// Updated all connected nodes
+			let node_distance: f64 = paths.distances[node_index].unwrap();
+			for arc in self.arcs(node_index) {
+				let head_node = self.arc_heads[arc];
+				if paths.settled[head_node] {
+					// Includes nodes connected to themselves ...
+					// I love this dataset
+					continue;
+				}
+				
+				let next_distance = node_distance + self.arc_costs[arc];
+				match paths.distances[head_node] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+				
+				paths.distances[head_node] = Some(next_distance);
+				paths.traceback_arcs[head_node] = Some((node_index, arc));
+				
+				// The end node is not reachable from the next node
+				let estimate = next_distance + lower_bound(head_node);
+				if estimate.is_infinite() {
+					continue;
+				}
+				
+				// Use negative numbers since we only have a max-heap
+				active_nodes.push((-(estimate * 1000.0) as i64, head_node));
+			}
+		}
+	}
+	
+	/// Follow the traceback arcs from the end node to the start of the search.
+	///
+	/// The traceback arcs hold the tail node and the id of the arc. The
+	/// result is a tuple of the distance, the travel time and the nodes
+	/// on the path in travel order.
+	fn trace_path(&self, traceback_arcs: &Vec<Option<(usize, usize)>>, end_node: usize,
+			max_speed: u64) -> (f64, f64, Box<Vec<usize>>) {
+		let mut path = Box::new(vec![end_node]);
+		let mut distance = 0.0;
+		let mut time = 0.0;
+		
+		let mut node = end_node;
+		while let Some((tail_node, arc)) = traceback_arcs[node] {
+			distance += self.arc_distances[arc] as f64;
+			time += self.arc_travel_time(arc, max_speed);
+			
+			path.push(tail_node);
+			node = tail_node;
+		}
+		path.reverse();
+		
+		return (distance, time, path);
+	}
+	
+	/// Compute distance and travel time of the path to the end node.
+	pub fn travel_to(&self, paths: &ShortestPaths, end_node: usize, max_speed: u64) -> (f64, f64) {
+		let (distance, time, _) = self.trace_path(&paths.traceback_arcs, end_node, max_speed);
+		
+		return (distance, time);
+	}
+	
+	pub fn generate_mapbb(&self, paths: &ShortestPaths, str: &mut String, end_node: usize) {
+		let mut path = vec![end_node];
+		let mut node = end_node;
+		
+		// Follow to previous node
+		while let Some((tail_node, _)) = paths.traceback_arcs[node] {
+			path.push(tail_node);
+			node = tail_node;
+		}
+		
+		self.append_mapbb(&path, str);
+	}
+	
+	/// Append the coordinates of every MAPBB_NODE_DIVIDER-th node and of the last node.
+	fn append_mapbb(&self, path: &[usize], str: &mut String) {
+		for (index, &node) in path.iter().enumerate() {
+			if index % MAPBB_NODE_DIVIDER as usize != 0 && index + 1 != path.len() {
+				continue;
+			}
+			
+			if index != 0 {
+				str.push_str(" ");
+			}
+			
+			let node = &self.nodes[node];
+			str.push_str(format!("{:.4},{:.4}", node.latitude, node.longitude).as_str());
+		}
+	}
+}
+
+impl Display for Graph {
+	fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+		try!(write!(fmt, "["));
+		for i in 0..self.nodes.len() {
+			for arc in self.arcs(i) {
+				try!(write!(fmt, "{}->{}({})", i, self.arc_heads[arc], self.arc_costs[arc]));
+
+				if arc + 1 != self.num_arcs() {
+					try!(write!(fmt, ", "));
+				}
+			}
+		}
+		try!(write!(fmt, "]"));
+
+		return Ok(());
+	}
+}
+
+#[test]
+fn test_read() {
+	let graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	assert_eq!("[0->1(30), 0->2(70), 1->2(20), 2->3(50), 3->1(40), 4->3(20)]",
+		format!("{}", graph));
+}
+
+#[test]
+fn test_read_modes() {
+	let graph = Graph::read_graph_from_file_with_mode("graphs/test.zip", LoadMode::Directed).unwrap();
+	assert_eq!(None, graph.compute_shortest_paths(0).distance(4));
+	assert_eq!(Some(100.0), graph.compute_shortest_paths(0).distance(3));
+	
+	let graph = Graph::read_graph_from_file_with_mode("graphs/test.zip", LoadMode::Undirected).unwrap();
+	assert_eq!("[0->1(30), 0->2(70), 1->0(30), 1->2(20), 1->3(40), 2->0(70), 2->1(20), 2->3(50), \
+		3->2(50), 3->1(40), 3->4(20), 4->3(20)]", format!("{}", graph));
+	assert_eq!(Some(90.0), graph.compute_shortest_paths(0).distance(4));
+	assert_eq!(Some(90.0), graph.compute_shortest_paths(4).distance(0));
+	
+	// The distances from a node are the distances to it in the directed graph
+	let graph = Graph::read_graph_from_file_with_mode("graphs/test.zip", LoadMode::Reverse).unwrap();
+	assert_eq!("[1->0(30), 1->3(40), 2->0(70), 2->1(20), 3->2(50), 3->4(20)]", format!("{}", graph));
+	assert_eq!(Some(100.0), graph.compute_shortest_paths(3).distance(0));
+	assert_eq!(None, graph.compute_shortest_paths(0).distance(3));
+	
+	// Opposite arcs in the file are not doubled
+	let graph = Graph::read_graph_from_file_with_mode("graphs/test3.zip", LoadMode::Undirected).unwrap();
+	assert_eq!(24, graph.num_arcs());
+	assert_eq!(Some(5000.0), graph.compute_shortest_paths(5).distance(6));
+}
+
+#[test]
+fn test_reachable_nodes() {
+	let graph = Graph::read_graph_from_file("graphs/test2.zip").unwrap();
+
+	assert_eq!(4, graph.compute_reachable_nodes(0).0);
+	assert_eq!(6, graph.compute_reachable_nodes(4).0);
+	assert_eq!(1, graph.compute_reachable_nodes(6).0);
+}
+
+#[test]
+fn test_shortest() {
+	let graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	assert_eq!("[0->1(30), 0->2(70), 1->2(20), 2->3(50), 3->1(40), 4->3(20)]",
+		format!("{}", graph));
+
+	let paths = graph.compute_shortest_paths(1);
+
+	let expected = vec![None, Some(0.0), Some(20.0), Some(70.0), None];
+	assert_eq!(expected, *paths.distances());
+	assert_eq!(3, paths.num_settled());
+	assert_eq!((70.0, 3), paths.get_furthest_node());
+	assert_eq!((70.0, 8.4), graph.travel_to(&paths, 3, 100));
+}
+
+#[test]
+fn test_shortest_side_by_side() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let distance_paths = graph.compute_shortest_paths(0);
+	graph.set_arc_costs_to_travel_time(130);
+	let time_paths = graph.compute_shortest_paths(0);
+
+	assert_eq!(Some(3650.0), distance_paths.distance(5));
+	assert_eq!((3650.0, 3700.0), (graph.travel_to(&distance_paths, 5, 130).0,
+		graph.travel_to(&time_paths, 5, 130).0));
+}
+
+#[test]
+fn test_shortest_parallel() {
+	use std::sync::Arc as SharedRef;
+	use std::thread;
+
+	let graph = SharedRef::new(Graph::read_graph_from_file("graphs/test3.zip").unwrap());
+	let expected: Vec<Vec<Option<f64>>> = (0..graph.num_nodes())
+		.map(|node| graph.compute_shortest_paths(node).distances().clone())
+		.collect();
+
+	let threads: Vec<_> = (0..graph.num_nodes()).map(|node| {
+		let graph = graph.clone();
+		thread::spawn(move || graph.compute_shortest_paths(node).distances().clone())
+	}).collect();
+
+	for (thread, expected) in threads.into_iter().zip(expected.into_iter()) {
+		assert_eq!(expected, thread.join().unwrap());
+	}
+}
+
+#[test]
+fn test_costs_distance() {
+	let mut graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	assert_eq!("[0->1(30), 0->2(70), 1->2(20), 2->3(50), 3->1(40), 4->3(20)]",
+		format!("{}", graph));
+
+	graph.set_arc_costs_to_travel_time(100);
+	graph.set_arc_costs_to_distance();
+
+	assert_eq!("[0->1(30), 0->2(70), 1->2(20), 2->3(50), 3->1(40), 4->3(20)]",
+		format!("{}", graph));
+}
+
+#[test]
+fn test_costs_time() {
+	let mut graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	assert_eq!("[0->1(30), 0->2(70), 1->2(20), 2->3(50), 3->1(40), 4->3(20)]",
+		format!("{}", graph));
+
+	graph.set_arc_costs_to_travel_time(100);
+
+	assert_eq!("[0->1(3.6), 0->2(8.4), 1->2(2.4), 2->3(6), 3->1(4.8), 4->3(2.4)]",
+		format!("{}", graph));
+}
+
+#[test]
+fn test_shortest_bidirectional() {
+	let mut graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	let (distance, time, path) = graph.compute_shortest_path_bidirectional(0, 3, 100).unwrap();
+	assert_eq!(100.0, distance);
+	assert_eq!(12.0, time);
+	assert_eq!(vec![0, 1, 2, 3], *path);
+
+	let (distance, _, path) = graph.compute_shortest_path_bidirectional(4, 2, 100).unwrap();
+	assert_eq!(80.0, distance);
+	assert_eq!(vec![4, 3, 1, 2], *path);
+
+	let (distance, _, path) = graph.compute_shortest_path_bidirectional(2, 2, 100).unwrap();
+	assert_eq!(0.0, distance);
+	assert_eq!(vec![2], *path);
+
+	assert!(graph.compute_shortest_path_bidirectional(1, 4, 100).is_none());
+
+	// Time costs select the same path, but report the travel time
+	graph.set_arc_costs_to_travel_time(100);
+	let (distance, time, _) = graph.compute_shortest_path_bidirectional(0, 3, 100).unwrap();
+	assert_eq!(100.0, distance);
+	assert_eq!(12.0, time);
+}
+
+#[test]
+fn test_shortest_astar() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let (distance, time, path, _) = graph.compute_shortest_path_astar(0, 5, 130).unwrap();
+	assert_eq!(3650.0, distance);
+	assert_eq!(438.0, time.round());
+	assert_eq!(vec![0, 2, 5], *path);
+
+	graph.set_arc_costs_to_travel_time(130);
+	let (distance, time, path, _) = graph.compute_shortest_path_astar(0, 5, 130).unwrap();
+	assert_eq!(3700.0, distance);
+	assert_eq!(133.2, (time * 10.0).round() / 10.0);
+	assert_eq!(vec![0, 3, 5], *path);
+
+	assert!(graph.compute_shortest_path_astar(5, 6, 130).is_none());
+}
+
+#[test]
+fn test_astar_settles_less() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	for start in 0..graph.num_nodes() {
+		for end in 0..graph.num_nodes() {
+			let dijkstra = graph.compute_shortest_path_dijkstra(start, end, 130);
+			let astar = graph.compute_shortest_path_astar(start, end, 130);
+
+			assert_eq!(dijkstra.is_some(), astar.is_some());
+			if let (Some(dijkstra), Some(astar)) = (dijkstra, astar) {
+				assert_eq!(dijkstra.0, astar.0);
+				assert!(astar.3 <= dijkstra.3);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_arc_layout() {
+	let graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	assert_eq!(6, graph.num_arcs());
+	assert_eq!(vec![0, 2, 3, 4, 5, 6], graph.arc_offsets);
+	assert_eq!(vec![1, 2], graph.arcs(0).map(|arc| graph.arc_heads[arc]).collect::<Vec<usize>>());
+	assert_eq!(vec![2, 4], graph.reverse_arcs(3).map(|index| graph.reverse_arc_tails[index]).collect::<Vec<usize>>());
+	assert_eq!(vec![3, 5], graph.reverse_arcs(3).map(|index| graph.reverse_arc_ids[index]).collect::<Vec<usize>>());
+	assert_eq!(1, graph.arcs(4).len());
+}
+
+#[test]
+fn test_write_graph() {
+	let graph = Graph::read_graph_from_file("graphs/test2.zip").unwrap();
+	let name = ::std::env::temp_dir().join("ex13_test_write_graph.zip");
+	let name = name.to_str().unwrap();
+
+	graph.write_graph_to_file(name).unwrap();
+	let written = Graph::read_graph_from_file(name).unwrap();
+
+	assert_eq!(format!("{}", graph), format!("{}", written));
+	assert_eq!(graph.arc_max_speeds, written.arc_max_speeds);
+	assert_eq!(graph.nodes[2].latitude, written.nodes[2].latitude);
+	assert_eq!(Some(3), written.node_with_id(3));
+	assert_eq!(None, written.node_with_id(7));
+}
\ No newline at end of file
@@ -0,0 +1,312 @@
+extern crate xml;
+
+use super::{Error, Graph, Node};
+use super::pbf;
+
+use self::xml::attribute::OwnedAttribute;
+use self::xml::reader::{EventReader, XmlEvent};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::result::Result;
+use std::string::String;
+use std::vec::Vec;
+
+/// Speed in km/h used for roads without speed limit.
+const UNLIMITED_SPEED: u64 = 130;
+
+/// Speed in km/h used for walking speed limits.
+const WALKING_SPEED: u64 = 7;
+
+/// Direction in which a way may be used.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Oneway {
+	No,
+	Forward,
+	Backward
+}
+
+/// Road of the OpenStreetMap data with its node ids.
+pub struct Way {
+	pub nodes: Vec<i64>,
+	pub max_speed: u64,
+	pub oneway: Oneway
+}
+
+/// Node coordinates and the roads read from OpenStreetMap data.
+pub struct OsmData {
+	pub coordinates: HashMap<i64, (f64, f64)>,
+	pub ways: Vec<Way>
+}
+
+impl OsmData {
+
+	pub fn new() -> OsmData {
+		OsmData {
+			coordinates: HashMap::new(),
+			ways: Vec::new()
+		}
+	}
+
+	/// Add the way if its tags describe a road for cars.
+	pub fn add_way(&mut self, nodes: Vec<i64>, tags: &HashMap<String, String>) {
+		let highway = match tags.get("highway") {
+			Some(highway) => highway,
+			None => { return; }
+		};
+
+		let default_speed = match highway_default_speed(highway) {
+			Some(speed) => speed,
+			None => { return; }
+		};
+
+		let max_speed = tags.get("maxspeed")
+			.and_then(|value| parse_max_speed(value))
+			.unwrap_or(default_speed);
+
+		// Motorways and roundabouts are oneway without explicit tag
+		let implied_oneway = highway == "motorway"
+			|| tags.get("junction").map_or(false, |junction| junction == "roundabout");
+		let oneway = match tags.get("oneway").map(|value| value.as_str()) {
+			Some("yes") | Some("true") | Some("1") => Oneway::Forward,
+			Some("-1") | Some("reverse") => Oneway::Backward,
+			Some("no") | Some("false") | Some("0") => Oneway::No,
+			_ => if implied_oneway { Oneway::Forward } else { Oneway::No }
+		};
+
+		self.ways.push(Way {
+			nodes: nodes,
+			max_speed: max_speed,
+			oneway: oneway
+		});
+	}
+}
+
+/// Returns the speed in km/h for roads of the highway class
+/// or None if the class is not used by cars.
+pub fn highway_default_speed(highway: &str) -> Option<u64> {
+	match highway {
+		"motorway" => Some(UNLIMITED_SPEED),
+		"trunk" => Some(110),
+		"primary" | "secondary" => Some(100),
+		"tertiary" => Some(70),
+		"motorway_link" | "trunk_link" => Some(60),
+		"primary_link" | "secondary_link" | "tertiary_link" => Some(50),
+		"unclassified" | "road" => Some(50),
+		"residential" => Some(30),
+		"service" => Some(20),
+		"living_street" => Some(WALKING_SPEED),
+		_ => None
+	}
+}
+
+/// Parse the value of a maxspeed tag to km/h.
+///
+/// Supports plain numbers, mph values, "none", "walk" and zone
+/// values like "DE:urban". Returns None for unknown values.
+pub fn parse_max_speed(value: &str) -> Option<u64> {
+	// Take the first of multiple values
+	let value = value.split(';').next().unwrap_or("").trim();
+
+	let speed = match value {
+		"none" => Some(UNLIMITED_SPEED),
+		"walk" => Some(WALKING_SPEED),
+		_ if value.contains(':') => {
+			match value.splitn(2, ':').nth(1) {
+				Some("urban") => Some(50),
+				Some("rural") => Some(100),
+				Some("motorway") => Some(UNLIMITED_SPEED),
+				Some("living_street") | Some("walk") => Some(WALKING_SPEED),
+				_ => None
+			}
+		},
+		_ if value.ends_with("mph") => {
+			value.trim_end_matches("mph").trim().parse::<f64>().ok()
+				.map(|speed| (speed * 1.609344).round() as u64)
+		},
+		_ => {
+			value.trim_end_matches("km/h").trim().parse::<f64>().ok()
+				.map(|speed| speed.round() as u64)
+		}
+	};
+
+	// A speed of zero would make the roads impassable
+	match speed {
+		Some(0) => None,
+		_ => speed
+	}
+}
+
+/// Returns the value of the attribute with the given name.
+fn attribute<'a>(attributes: &'a Vec<OwnedAttribute>, name: &str) -> Result<&'a str, Error> {
+	match attributes.iter().find(|attribute| attribute.name.local_name == name) {
+		Some(attribute) => Ok(&attribute.value),
+		None => Err(Error::from(format!("Invalid OSM file! (Missing attribute {})", name)))
+	}
+}
+
+/// Read the nodes and roads from OpenStreetMap XML data.
+fn read_xml<R: Read>(input: R) -> Result<OsmData, Error> {
+	let mut data = OsmData::new();
+
+	// Node ids and tags of the current way
+	let mut way: Option<(Vec<i64>, HashMap<String, String>)> = None;
+
+	for event in EventReader::new(input) {
+		let event = match event {
+			Ok(event) => event,
+			Err(error) => { return Err(Error::from(format!("Invalid OSM file! ({})", error))); }
+		};
+
+		match event {
+			XmlEvent::StartElement { name, attributes, .. } => {
+				match name.local_name.as_str() {
+					"node" => {
+						let id = try!(try!(attribute(&attributes, "id")).parse::<i64>());
+						let latitude = try!(try!(attribute(&attributes, "lat")).parse::<f64>());
+						let longitude = try!(try!(attribute(&attributes, "lon")).parse::<f64>());
+						data.coordinates.insert(id, (latitude, longitude));
+					},
+					"way" => {
+						way = Some((Vec::new(), HashMap::new()));
+					},
+					"nd" => {
+						if let Some((ref mut nodes, _)) = way {
+							nodes.push(try!(try!(attribute(&attributes, "ref")).parse::<i64>()));
+						}
+					},
+					"tag" => {
+						if let Some((_, ref mut tags)) = way {
+							let key = try!(attribute(&attributes, "k")).to_owned();
+							let value = try!(attribute(&attributes, "v")).to_owned();
+							tags.insert(key, value);
+						}
+					},
+					_ => { }
+				}
+			},
+			XmlEvent::EndElement { name } => {
+				if name.local_name == "way" {
+					if let Some((nodes, tags)) = way.take() {
+						data.add_way(nodes, &tags);
+					}
+				}
+			},
+			_ => { }
+		}
+	}
+
+	Ok(data)
+}
+
+impl Graph {
+
+	/// Import the roads of an OpenStreetMap file.
+	///
+	/// Files ending with ".pbf" are read as protocol buffer files,
+	/// all others as XML files. Only nodes on roads are kept,
+	/// they are ordered by their OpenStreetMap id and numbered from zero
+	/// like the nodes of graph files.
+	pub fn read_graph_from_osm_file(name: &str) -> Result<Graph, Error> {
+		let file = try!(File::open(name));
+
+		let data = match name.ends_with(".pbf") {
+			true => try!(pbf::read_pbf(BufReader::new(file))),
+			false => try!(read_xml(BufReader::new(file)))
+		};
+
+		Ok(Graph::from_osm_data(data))
+	}
+
+	/// Build the graph of the roads with arcs between consecutive way nodes.
+	///
+	/// Road segments with nodes without coordinates are left out.
+	fn from_osm_data(data: OsmData) -> Graph {
+		let mut ids: Vec<i64> = data.ways.iter()
+			.flat_map(|way| way.nodes.iter().cloned())
+			.filter(|id| data.coordinates.contains_key(id))
+			.collect();
+		ids.sort();
+		ids.dedup();
+
+		let mut graph = Graph::new();
+		let mut node_indices = HashMap::with_capacity(ids.len());
+
+		for (index, &id) in ids.iter().enumerate() {
+			let (latitude, longitude) = data.coordinates[&id];
+			node_indices.insert(id, index);
+
+			graph.nodes.push(Node {
+				id: index,
+				latitude: latitude,
+				longitude: longitude
+			});
+		}
+
+		let mut arcs = Vec::new();
+		for way in data.ways.iter() {
+			for segment in way.nodes.windows(2) {
+				let (tail_node, head_node) = match (node_indices.get(&segment[0]), node_indices.get(&segment[1])) {
+					(Some(&tail_node), Some(&head_node)) => (tail_node, head_node),
+					_ => { continue; }
+				};
+
+				let distance = graph.nodes[tail_node].great_circle_distance(&graph.nodes[head_node]).round() as u64;
+
+				if way.oneway != Oneway::Backward {
+					arcs.push((tail_node, head_node, distance, way.max_speed));
+				}
+				if way.oneway != Oneway::Forward {
+					arcs.push((head_node, tail_node, distance, way.max_speed));
+				}
+			}
+		}
+
+		graph.build_arcs(arcs);
+		return graph;
+	}
+}
+
+#[test]
+fn test_max_speed() {
+	assert_eq!(Some(50), parse_max_speed("50"));
+	assert_eq!(Some(48), parse_max_speed("30 mph"));
+	assert_eq!(Some(100), parse_max_speed("DE:rural"));
+	assert_eq!(Some(UNLIMITED_SPEED), parse_max_speed("none"));
+	assert_eq!(Some(70), parse_max_speed("70;50"));
+	assert_eq!(None, parse_max_speed("signals"));
+	assert_eq!(None, parse_max_speed("0"));
+	assert_eq!(None, highway_default_speed("footway"));
+}
+
+#[test]
+fn test_read_osm() {
+	let graph = Graph::read_graph_from_osm_file("graphs/test.osm").unwrap();
+
+	// The footway node and the node without coordinates are left out
+	assert_eq!(vec![0, 1, 2, 3, 4], graph.nodes.iter().map(|node| node.id).collect::<Vec<_>>());
+	assert_eq!((47.99, 7.8), (graph.nodes[4].latitude, graph.nodes[4].longitude));
+	assert_eq!("[0->1(744), 0->3(1112), 0->4(1112), 1->0(744), 1->2(1112), 2->1(1112), 2->3(744), 4->0(1112)]",
+		format!("{}", graph));
+
+	// Primary with maxspeed, tertiary with mph and residential with zone
+	assert_eq!(70, graph.arc_max_speeds[graph.arcs(0).start]);
+	assert_eq!(48, graph.arc_max_speeds[graph.arcs(0).start + 1]);
+	assert_eq!(50, graph.arc_max_speeds[graph.arcs(0).start + 2]);
+	assert_eq!(30, graph.arc_max_speeds[graph.arcs(2).start + 1]);
+}
+
+#[test]
+fn test_read_osm_pbf() {
+	let xml_graph = Graph::read_graph_from_osm_file("graphs/test.osm").unwrap();
+	let pbf_graph = Graph::read_graph_from_osm_file("graphs/test.osm.pbf").unwrap();
+
+	assert_eq!(format!("{}", xml_graph), format!("{}", pbf_graph));
+	assert_eq!(xml_graph.arc_max_speeds, pbf_graph.arc_max_speeds);
+	for (xml_node, pbf_node) in xml_graph.nodes.iter().zip(pbf_graph.nodes.iter()) {
+		assert_eq!(xml_node.id, pbf_node.id);
+		assert!((xml_node.latitude - pbf_node.latitude).abs() < 1e-7);
+		assert!((xml_node.longitude - pbf_node.longitude).abs() < 1e-7);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,150 @@
+use super::Graph;
+
+use std::collections::BinaryHeap;
+use std::vec::Vec;
+
+/// Partial path of the multi-criteria search.
+struct Label {
+	node: usize,
+	distance: f64,
+	time: f64,
+	previous: Option<usize>,
+	/// Label was dominated by a later label before it was settled.
+	removed: bool
+}
+
+impl Label {
+	/// Returns if the label is at most a factor of 1 + epsilon worse in both criteria.
+	fn dominates(&self, other: &Label, epsilon: f64) -> bool {
+		self.distance <= (1.0 + epsilon) * other.distance && self.time <= (1.0 + epsilon) * other.time
+	}
+}
+
+impl Graph {
+
+	/// Compute the Pareto front of paths between two nodes trading distance against travel time.
+	///
+	/// Runs a label-setting search which settles the labels in lexicographic
+	/// order of distance and time. Labels within a factor of 1 + epsilon of
+	/// another label at the same node are pruned, an epsilon of zero gives the
+	/// exact front. The result contains the distance, the travel time with the
+	/// given max speed and the nodes of each path, ordered by ascending distance.
+	pub fn compute_pareto_paths(&self, start_node: usize, end_node: usize, max_speed: u64,
+			epsilon: f64) -> Vec<(f64, f64, Box<Vec<usize>>)> {
+		let mut labels = vec![Label { node: start_node, distance: 0.0, time: 0.0, previous: None, removed: false }];
+		let mut node_labels: Vec<Vec<usize>> = vec![Vec::new(); self.num_nodes()];
+		let mut end_labels = Vec::new();
+
+		let mut active_labels = BinaryHeap::<(i64, i64, usize)>::new();
+		node_labels[start_node].push(0);
+		active_labels.push((0, 0, 0));
+
+		while let Some((_, _, label_index)) = active_labels.pop() {
+			if labels[label_index].removed {
+				continue;
+			}
+
+			let node = labels[label_index].node;
+			if node == end_node {
+				end_labels.push(label_index);
+				continue;
+			}
+
+			for arc in self.arcs(node) {
+				let label = Label {
+					node: self.arc_heads[arc],
+					distance: labels[label_index].distance + self.arc_distances[arc] as f64,
+					time: labels[label_index].time + self.arc_travel_time(arc, max_speed),
+					previous: Some(label_index),
+					removed: false
+				};
+
+				// Paths which are not better than a known path to the end node are of no interest
+				let dominated = |&other: &usize| labels[other].dominates(&label, epsilon);
+				if node_labels[label.node].iter().any(&dominated) || node_labels[end_node].iter().any(&dominated) {
+					continue;
+				}
+
+				// Remove the labels dominated by the new label, settled labels are never dominated
+				let head_node = label.node;
+				let mut index = 0;
+				while index < node_labels[head_node].len() {
+					let other = node_labels[head_node][index];
+					if label.dominates(&labels[other], 0.0) {
+						labels[other].removed = true;
+						node_labels[head_node].swap_remove(index);
+					} else {
+						index += 1;
+					}
+				}
+
+				// Use negative numbers since we only have a max-heap
+				active_labels.push((-(label.distance * 1000.0) as i64, -(label.time * 1000.0) as i64, labels.len()));
+				node_labels[head_node].push(labels.len());
+				labels.push(label);
+			}
+		}
+
+		let mut paths = Vec::with_capacity(end_labels.len());
+		for &end_label in end_labels.iter() {
+			let mut path = Box::new(vec![end_node]);
+			let mut label = &labels[end_label];
+			while let Some(previous) = label.previous {
+				label = &labels[previous];
+				path.push(label.node);
+			}
+			path.reverse();
+
+			paths.push((labels[end_label].distance, labels[end_label].time, path));
+		}
+
+		return paths;
+	}
+}
+
+#[test]
+fn test_pareto_paths() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let paths = graph.compute_pareto_paths(0, 5, 130, 0.0);
+	assert_eq!(2, paths.len());
+	assert_eq!((3650.0, vec![0, 2, 5]), (paths[0].0, *paths[0].2.clone()));
+	assert_eq!((3700.0, vec![0, 3, 5]), (paths[1].0, *paths[1].2.clone()));
+	assert!((paths[1].1 - 133.2).abs() < 1e-6);
+
+	// The same speed on all arcs makes the shortest path also the fastest one
+	let paths = graph.compute_pareto_paths(0, 5, 30, 0.0);
+	assert_eq!(1, paths.len());
+	assert_eq!(vec![0, 2, 5], *paths[0].2);
+
+	let paths = graph.compute_pareto_paths(0, 5, 130, 10.0);
+	assert_eq!(1, paths.len());
+
+	assert!(graph.compute_pareto_paths(5, 6, 130, 0.0).is_empty());
+	assert_eq!(vec![(0.0, 0.0, Box::new(vec![3]))], graph.compute_pareto_paths(3, 3, 130, 0.0));
+}
+
+#[test]
+fn test_pareto_extremes() {
+	let mut graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	// The first path is the shortest, the last one the fastest
+	for start in 0..graph.num_nodes() {
+		for end in 0..graph.num_nodes() {
+			let paths = graph.compute_pareto_paths(start, end, 130, 0.0);
+
+			graph.set_arc_costs_to_distance();
+			let shortest = graph.compute_shortest_path_dijkstra(start, end, 130);
+			graph.set_arc_costs_to_travel_time(130);
+			let fastest = graph.compute_shortest_path_dijkstra(start, end, 130);
+
+			match (shortest, fastest) {
+				(Some(shortest), Some(fastest)) => {
+					assert_eq!(shortest.0, paths[0].0);
+					assert!((fastest.1 - paths[paths.len() - 1].1).abs() < 1e-6);
+				},
+				_ => { assert!(paths.is_empty()); }
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,411 @@
+extern crate flate2;
+
+use super::Error;
+use super::osm::OsmData;
+
+use self::flate2::read::ZlibDecoder;
+
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read};
+use std::result::Result;
+use std::string::String;
+use std::vec::Vec;
+
+/// Largest allowed size of a blob header and blob, as given by the format.
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+/// Required features of a file we are able to read.
+const SUPPORTED_FEATURES: [&str; 2] = ["OsmSchema-V0.6", "DenseNodes"];
+
+/// Value of a protocol buffer field.
+enum Value<'a> {
+	Varint(u64),
+	Bytes(&'a [u8]),
+	Fixed
+}
+
+/// Reader for the fields of a protocol buffer message.
+struct Message<'a> {
+	data: &'a [u8],
+	position: usize
+}
+
+impl<'a> Message<'a> {
+
+	fn new(data: &'a [u8]) -> Message<'a> {
+		Message {
+			data: data,
+			position: 0
+		}
+	}
+
+	fn read_varint(&mut self) -> Result<u64, Error> {
+		let mut value = 0;
+
+		for shift in 0..10 {
+			let byte = match self.data.get(self.position) {
+				Some(&byte) => byte,
+				None => { return Err(Error::from("Invalid PBF file! (Unexpected end of message)")); }
+			};
+			self.position += 1;
+
+			value |= ((byte & 0x7f) as u64) << (shift * 7);
+			if byte & 0x80 == 0 {
+				return Ok(value);
+			}
+		}
+
+		Err(Error::from("Invalid PBF file! (Invalid varint)"))
+	}
+
+	fn skip(&mut self, length: usize) -> Result<&'a [u8], Error> {
+		if self.data.len() - self.position < length {
+			return Err(Error::from("Invalid PBF file! (Unexpected end of message)"));
+		}
+
+		let bytes = &self.data[self.position .. self.position + length];
+		self.position += length;
+		Ok(bytes)
+	}
+
+	/// Returns the next field number and value or None at the end of the message.
+	fn next_field(&mut self) -> Result<Option<(u64, Value<'a>)>, Error> {
+		if self.position == self.data.len() {
+			return Ok(None);
+		}
+
+		let key = try!(self.read_varint());
+		let value = match key & 0x7 {
+			0 => Value::Varint(try!(self.read_varint())),
+			1 => { try!(self.skip(8)); Value::Fixed },
+			2 => {
+				let length = try!(self.read_varint()) as usize;
+				Value::Bytes(try!(self.skip(length)))
+			},
+			5 => { try!(self.skip(4)); Value::Fixed },
+			_ => { return Err(Error::from("Invalid PBF file! (Unsupported wire type)")); }
+		};
+
+		Ok(Some((key >> 3, value)))
+	}
+}
+
+/// Decode a zigzag encoded signed value.
+fn zigzag(value: u64) -> i64 {
+	((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Returns the values of a packed or a single unpacked repeated field.
+fn varints(value: Value) -> Result<Vec<u64>, Error> {
+	match value {
+		Value::Varint(value) => Ok(vec![value]),
+		Value::Bytes(bytes) => {
+			let mut message = Message::new(bytes);
+			let mut values = Vec::new();
+
+			while message.position < bytes.len() {
+				values.push(try!(message.read_varint()));
+			}
+
+			Ok(values)
+		},
+		Value::Fixed => Err(Error::from("Invalid PBF file! (Expected varint)"))
+	}
+}
+
+/// Returns the bytes of a length delimited field.
+fn bytes<'a>(value: Value<'a>) -> Result<&'a [u8], Error> {
+	match value {
+		Value::Bytes(bytes) => Ok(bytes),
+		_ => Err(Error::from("Invalid PBF file! (Expected bytes)"))
+	}
+}
+
+/// Read the given number of bytes, returns None at the end of the file.
+fn read_exact_or_eof<R: Read>(input: &mut R, length: usize) -> Result<Option<Vec<u8>>, Error> {
+	let mut buffer = vec![0; length];
+	let mut filled = 0;
+
+	while filled < length {
+		match input.read(&mut buffer[filled..]) {
+			Ok(0) if filled == 0 => { return Ok(None); },
+			Ok(0) => { return Err(Error::from("Invalid PBF file! (Unexpected end of file)")); },
+			Ok(count) => { filled += count; },
+			Err(ref error) if error.kind() == ErrorKind::Interrupted => { },
+			Err(error) => { return Err(Error::from(error)); }
+		}
+	}
+
+	Ok(Some(buffer))
+}
+
+/// Returns the uncompressed content of a blob.
+fn read_blob(data: &[u8]) -> Result<Vec<u8>, Error> {
+	let mut message = Message::new(data);
+	let mut raw_size = None;
+	let mut compressed = None;
+	let mut content = None;
+
+	while let Some((field, value)) = try!(message.next_field()) {
+		match (field, value) {
+			(1, value) => { content = Some(try!(bytes(value)).to_vec()); },
+			(2, Value::Varint(size)) => { raw_size = Some(size as usize); },
+			(3, value) => { compressed = Some(try!(bytes(value))); },
+			(4..=7, _) => { return Err(Error::from("Invalid PBF file! (Unsupported compression)")); },
+			_ => { }
+		}
+	}
+
+	if let Some(compressed) = compressed {
+		// Never inflate more than the limit, the compressed data may be crafted
+		let mut uncompressed = Vec::with_capacity(::std::cmp::min(raw_size.unwrap_or(0), MAX_BLOB_SIZE));
+		try!(ZlibDecoder::new(compressed).take(MAX_BLOB_SIZE as u64 + 1).read_to_end(&mut uncompressed));
+
+		if uncompressed.len() > MAX_BLOB_SIZE {
+			return Err(Error::from("Invalid PBF file! (Blob too large)"));
+		}
+		if raw_size.map_or(false, |raw_size| raw_size != uncompressed.len()) {
+			return Err(Error::from("Invalid PBF file! (Wrong blob size)"));
+		}
+		content = Some(uncompressed);
+	}
+
+	match content {
+		Some(content) => Ok(content),
+		None => Err(Error::from("Invalid PBF file! (Empty blob)"))
+	}
+}
+
+/// Check that we support all required features of the header block.
+fn check_header_block(data: &[u8]) -> Result<(), Error> {
+	let mut message = Message::new(data);
+
+	while let Some((field, value)) = try!(message.next_field()) {
+		if field == 4 {
+			let feature = String::from_utf8_lossy(try!(bytes(value)));
+			if !SUPPORTED_FEATURES.contains(&feature.as_ref()) {
+				return Err(Error::from(format!("Invalid PBF file! (Unsupported feature {})", feature)));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Coordinate encoding and string table of a primitive block.
+struct BlockInfo<'a> {
+	strings: Vec<&'a [u8]>,
+	granularity: i64,
+	latitude_offset: i64,
+	longitude_offset: i64
+}
+
+impl<'a> BlockInfo<'a> {
+	fn coordinates(&self, latitude: i64, longitude: i64) -> (f64, f64) {
+		(1e-9 * (self.latitude_offset + self.granularity * latitude) as f64,
+			1e-9 * (self.longitude_offset + self.granularity * longitude) as f64)
+	}
+
+	fn string(&self, index: u64) -> Result<String, Error> {
+		match self.strings.get(index as usize) {
+			Some(string) => Ok(String::from_utf8_lossy(string).into_owned()),
+			None => Err(Error::from("Invalid PBF file! (Invalid string index)"))
+		}
+	}
+}
+
+/// Read the nodes and ways of a primitive block.
+fn read_primitive_block(data: &[u8], osm_data: &mut OsmData) -> Result<(), Error> {
+	let mut info = BlockInfo {
+		strings: Vec::new(),
+		granularity: 100,
+		latitude_offset: 0,
+		longitude_offset: 0
+	};
+	let mut groups = Vec::new();
+
+	// The groups may come before the block information
+	let mut message = Message::new(data);
+	while let Some((field, value)) = try!(message.next_field()) {
+		match (field, value) {
+			(1, value) => {
+				let mut table = Message::new(try!(bytes(value)));
+				while let Some((field, value)) = try!(table.next_field()) {
+					if field == 1 {
+						info.strings.push(try!(bytes(value)));
+					}
+				}
+			},
+			(2, value) => { groups.push(try!(bytes(value))); },
+			(17, Value::Varint(granularity)) => { info.granularity = granularity as i64; },
+			(19, Value::Varint(offset)) => { info.latitude_offset = offset as i64; },
+			(20, Value::Varint(offset)) => { info.longitude_offset = offset as i64; },
+			_ => { }
+		}
+	}
+
+	for group in groups.into_iter() {
+		let mut message = Message::new(group);
+		while let Some((field, value)) = try!(message.next_field()) {
+			match field {
+				1 => { try!(read_node(try!(bytes(value)), &info, osm_data)); },
+				2 => { try!(read_dense_nodes(try!(bytes(value)), &info, osm_data)); },
+				3 => { try!(read_way(try!(bytes(value)), &info, osm_data)); },
+				_ => { }
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn read_node(data: &[u8], info: &BlockInfo, osm_data: &mut OsmData) -> Result<(), Error> {
+	let mut message = Message::new(data);
+	let (mut id, mut latitude, mut longitude) = (0, 0, 0);
+
+	while let Some((field, value)) = try!(message.next_field()) {
+		match (field, value) {
+			(1, Value::Varint(value)) => { id = zigzag(value); },
+			(8, Value::Varint(value)) => { latitude = zigzag(value); },
+			(9, Value::Varint(value)) => { longitude = zigzag(value); },
+			_ => { }
+		}
+	}
+
+	osm_data.coordinates.insert(id, info.coordinates(latitude, longitude));
+	Ok(())
+}
+
+fn read_dense_nodes(data: &[u8], info: &BlockInfo, osm_data: &mut OsmData) -> Result<(), Error> {
+	let mut message = Message::new(data);
+	let (mut ids, mut latitudes, mut longitudes) = (Vec::new(), Vec::new(), Vec::new());
+
+	while let Some((field, value)) = try!(message.next_field()) {
+		match field {
+			1 => { ids.extend(try!(varints(value))); },
+			8 => { latitudes.extend(try!(varints(value))); },
+			9 => { longitudes.extend(try!(varints(value))); },
+			_ => { }
+		}
+	}
+
+	if ids.len() != latitudes.len() || ids.len() != longitudes.len() {
+		return Err(Error::from("Invalid PBF file! (Invalid dense nodes)"));
+	}
+
+	// All values are delta encoded
+	let (mut id, mut latitude, mut longitude) = (0, 0, 0);
+	for index in 0..ids.len() {
+		id += zigzag(ids[index]);
+		latitude += zigzag(latitudes[index]);
+		longitude += zigzag(longitudes[index]);
+		osm_data.coordinates.insert(id, info.coordinates(latitude, longitude));
+	}
+
+	Ok(())
+}
+
+fn read_way(data: &[u8], info: &BlockInfo, osm_data: &mut OsmData) -> Result<(), Error> {
+	let mut message = Message::new(data);
+	let (mut keys, mut values, mut refs) = (Vec::new(), Vec::new(), Vec::new());
+
+	while let Some((field, value)) = try!(message.next_field()) {
+		match field {
+			2 => { keys.extend(try!(varints(value))); },
+			3 => { values.extend(try!(varints(value))); },
+			8 => { refs.extend(try!(varints(value))); },
+			_ => { }
+		}
+	}
+
+	if keys.len() != values.len() {
+		return Err(Error::from("Invalid PBF file! (Invalid way tags)"));
+	}
+
+	let mut tags = HashMap::with_capacity(keys.len());
+	for (&key, &value) in keys.iter().zip(values.iter()) {
+		tags.insert(try!(info.string(key)), try!(info.string(value)));
+	}
+
+	// The node ids are delta encoded
+	let mut id = 0;
+	let nodes = refs.into_iter()
+		.map(|delta| { id += zigzag(delta); id })
+		.collect();
+
+	osm_data.add_way(nodes, &tags);
+	Ok(())
+}
+
+/// Read the nodes and roads from OpenStreetMap protocol buffer data.
+pub fn read_pbf<R: Read>(mut input: R) -> Result<OsmData, Error> {
+	let mut osm_data = OsmData::new();
+
+	loop {
+		// Each blob is preceded by its header and the big endian header length
+		let header_size = match try!(read_exact_or_eof(&mut input, 4)) {
+			Some(bytes) => bytes.iter().fold(0, |size, &byte| (size << 8) | byte as usize),
+			None => { break; }
+		};
+		if header_size > MAX_HEADER_SIZE {
+			return Err(Error::from("Invalid PBF file! (Blob header too large)"));
+		}
+
+		let header = match try!(read_exact_or_eof(&mut input, header_size)) {
+			Some(header) => header,
+			None => { return Err(Error::from("Invalid PBF file! (Unexpected end of file)")); }
+		};
+
+		let mut blob_type = String::new();
+		let mut blob_size = 0;
+		let mut message = Message::new(&header);
+		while let Some((field, value)) = try!(message.next_field()) {
+			match (field, value) {
+				(1, value) => { blob_type = String::from_utf8_lossy(try!(bytes(value))).into_owned(); },
+				(3, Value::Varint(size)) => { blob_size = size as usize; },
+				_ => { }
+			}
+		}
+		if blob_size > MAX_BLOB_SIZE {
+			return Err(Error::from("Invalid PBF file! (Blob too large)"));
+		}
+
+		let blob = match try!(read_exact_or_eof(&mut input, blob_size)) {
+			Some(blob) => blob,
+			None => { return Err(Error::from("Invalid PBF file! (Unexpected end of file)")); }
+		};
+
+		match blob_type.as_str() {
+			"OSMHeader" => { try!(check_header_block(&try!(read_blob(&blob)))); },
+			"OSMData" => { try!(read_primitive_block(&try!(read_blob(&blob)), &mut osm_data)); },
+			_ => { }
+		}
+	}
+
+	Ok(osm_data)
+}
+
+#[test]
+fn test_read_blob() {
+	use self::flate2::Compression;
+	use self::flate2::write::ZlibEncoder;
+	use std::io::Write;
+
+	let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+	encoder.write_all(&[0; 1000]).unwrap();
+	let compressed = encoder.finish().unwrap();
+
+	// Raw size of 1000 or 10 followed by the zlib data
+	let blob = |raw_size: &[u8]| {
+		let mut blob = vec![0x10];
+		blob.extend_from_slice(raw_size);
+		blob.push(0x1a);
+		blob.push(compressed.len() as u8);
+		blob.extend_from_slice(&compressed);
+		blob
+	};
+
+	assert_eq!(vec![0; 1000], read_blob(&blob(&[0xe8, 0x07])).unwrap());
+	assert!(read_blob(&blob(&[10])).is_err());
+}
\ No newline at end of file
@@ -0,0 +1,481 @@
+use super::Graph;
+
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::vec::Vec;
+
+/// Disjoint sets of nodes with path halving and union by rank.
+struct UnionFind {
+	parents: Vec<usize>,
+	ranks: Vec<u8>
+}
+
+impl UnionFind {
+	fn new(count: usize) -> UnionFind {
+		UnionFind {
+			parents: (0..count).collect(),
+			ranks: vec![0; count]
+		}
+	}
+
+	/// Returns the representative of the set containing the element.
+	fn find(&mut self, mut element: usize) -> usize {
+		while self.parents[element] != element {
+			let grandparent = self.parents[self.parents[element]];
+			self.parents[element] = grandparent;
+			element = grandparent;
+		}
+
+		return element;
+	}
+
+	/// Merge the sets of both elements, returns false if they already are in the same set.
+	fn union(&mut self, first: usize, second: usize) -> bool {
+		let (first, second) = (self.find(first), self.find(second));
+		if first == second {
+			return false;
+		}
+
+		if self.ranks[first] < self.ranks[second] {
+			self.parents[first] = second;
+		} else if self.ranks[first] > self.ranks[second] {
+			self.parents[second] = first;
+		} else {
+			self.parents[second] = first;
+			self.ranks[first] += 1;
+		}
+
+		return true;
+	}
+}
+
+/// Tree or forest of arcs, where the direction of the arcs is ignored.
+///
+/// Each tree is rooted at its lowest node and every other node of the tree
+/// stores the node and arc towards the root, like the traceback arcs of a
+/// shortest path search.
+pub struct SpanningTree {
+	nodes: Vec<usize>,
+	arcs: Vec<usize>,
+	costs: f64,
+	roots: Vec<usize>,
+	traceback_arcs: Vec<Option<(usize, usize)>>
+}
+
+impl SpanningTree {
+
+	/// Create the tree of the nodes connected by the arcs, which must not contain a cycle.
+	fn new(graph: &Graph, mut nodes: Vec<usize>, arcs: Vec<usize>) -> SpanningTree {
+		let mut incident_arcs: Vec<Vec<(usize, usize)>> = vec![Vec::new(); graph.num_nodes()];
+		for &arc in arcs.iter() {
+			let (tail_node, head_node) = (graph.arc_tail(arc), graph.arc_heads[arc]);
+			incident_arcs[tail_node].push((head_node, arc));
+			incident_arcs[head_node].push((tail_node, arc));
+			nodes.push(tail_node);
+			nodes.push(head_node);
+		}
+
+		nodes.sort();
+		nodes.dedup();
+
+		let mut visited = vec![false; graph.num_nodes()];
+		let mut traceback_arcs = vec![None; graph.num_nodes()];
+		let mut roots = Vec::new();
+		let mut queue = VecDeque::new();
+
+		for &root in nodes.iter() {
+			if visited[root] {
+				continue;
+			}
+
+			roots.push(root);
+			visited[root] = true;
+			queue.push_back(root);
+
+			while let Some(node) = queue.pop_front() {
+				for &(next_node, arc) in incident_arcs[node].iter() {
+					if !visited[next_node] {
+						visited[next_node] = true;
+						traceback_arcs[next_node] = Some((node, arc));
+						queue.push_back(next_node);
+					}
+				}
+			}
+		}
+
+		SpanningTree {
+			costs: arcs.iter().map(|&arc| graph.arc_costs[arc]).sum(),
+			nodes: nodes,
+			arcs: arcs,
+			roots: roots,
+			traceback_arcs: traceback_arcs
+		}
+	}
+
+	/// Returns the nodes of the tree in ascending order.
+	pub fn nodes(&self) -> &Vec<usize> {
+		&self.nodes
+	}
+
+	/// Returns the arcs of the tree.
+	pub fn arcs(&self) -> &Vec<usize> {
+		&self.arcs
+	}
+
+	/// Returns the sum of the arc costs.
+	pub fn costs(&self) -> f64 {
+		self.costs
+	}
+
+	/// Returns the root of each tree of the forest.
+	pub fn roots(&self) -> &Vec<usize> {
+		&self.roots
+	}
+
+	/// Returns the node and arc towards the root of the node, or None for
+	/// roots and nodes which are not part of the tree.
+	pub fn parent(&self, node: usize) -> Option<(usize, usize)> {
+		self.traceback_arcs[node]
+	}
+
+	/// Split the tree into paths, so that each arc is part of exactly one path.
+	///
+	/// Each path starts at a leaf and leads towards the root, up to the first
+	/// node which is part of an earlier path or the root.
+	pub fn branches(&self) -> Vec<Vec<usize>> {
+		let mut has_children = vec![false; self.traceback_arcs.len()];
+		for &node in self.nodes.iter() {
+			if let Some((parent, _)) = self.traceback_arcs[node] {
+				has_children[parent] = true;
+			}
+		}
+
+		let mut covered = vec![false; self.traceback_arcs.len()];
+		let mut branches = Vec::new();
+		for &leaf in self.nodes.iter().filter(|&&node| !has_children[node] && self.traceback_arcs[node].is_some()) {
+			let mut branch = vec![leaf];
+			let mut node = leaf;
+			covered[leaf] = true;
+
+			while let Some((parent, _)) = self.traceback_arcs[node] {
+				branch.push(parent);
+				if covered[parent] {
+					break;
+				}
+				covered[parent] = true;
+				node = parent;
+			}
+
+			branches.push(branch);
+		}
+
+		return branches;
+	}
+
+	/// Append a [map] tag for every branch of the tree, each on its own line.
+	///
+	/// The coordinates of the branches are thinned out like those of generate_mapbb.
+	pub fn generate_mapbb(&self, graph: &Graph, str: &mut String) {
+		for branch in self.branches() {
+			str.push_str("[map]");
+			graph.append_mapbb(&branch, str);
+			str.push_str("[/map]\n");
+		}
+	}
+}
+
+impl Graph {
+
+	/// Compute the minimum spanning forest with Kruskal's algorithm.
+	///
+	/// The arcs are treated as undirected, so the forest spans the weakly
+	/// connected components with the arc costs of the current metric. Of
+	/// parallel and opposite arcs only the cheapest one is used.
+	pub fn compute_spanning_forest_kruskal(&self) -> SpanningTree {
+		let mut arcs: Vec<usize> = (0..self.num_arcs()).collect();
+		arcs.sort_by(|&first, &second| (self.arc_costs[first], first).partial_cmp(&(self.arc_costs[second], second)).unwrap());
+
+		let arcs = self.select_forest_arcs(arcs);
+		return SpanningTree::new(self, (0..self.num_nodes()).collect(), arcs);
+	}
+
+	/// Compute the minimum spanning forest with Prim's algorithm.
+	///
+	/// Grows a tree from the lowest node not part of a tree yet, until all
+	/// nodes are part of a tree. The result has the same costs as Kruskal's
+	/// algorithm, but may use other arcs of the same costs.
+	pub fn compute_spanning_forest_prim(&self) -> SpanningTree {
+		let mut in_tree = vec![false; self.num_nodes()];
+		let mut arcs = Vec::with_capacity(self.num_nodes());
+		let mut active_arcs = BinaryHeap::<(i64, usize, usize)>::new();
+
+		for root in 0..self.num_nodes() {
+			if in_tree[root] {
+				continue;
+			}
+
+			in_tree[root] = true;
+			self.push_undirected_arcs(root, &in_tree, &mut active_arcs);
+
+			while let Some((_, node, arc)) = active_arcs.pop() {
+				if in_tree[node] {
+					continue;
+				}
+
+				in_tree[node] = true;
+				arcs.push(arc);
+				self.push_undirected_arcs(node, &in_tree, &mut active_arcs);
+			}
+		}
+
+		return SpanningTree::new(self, (0..self.num_nodes()).collect(), arcs);
+	}
+
+	/// Compute a tree connecting all terminal nodes with at most twice the
+	/// costs of the minimum Steiner tree.
+	///
+	/// Uses Mehlhorn's algorithm: a Dijkstra search from all terminals at
+	/// once assigns each node its nearest terminal, the minimum spanning tree
+	/// of the terminals connected by shortest paths across these regions is
+	/// expanded to its paths. Arcs are treated as undirected. Returns None if
+	/// the terminals are not connected.
+	pub fn compute_steiner_tree(&self, terminals: &Vec<usize>) -> Option<SpanningTree> {
+		let mut terminals = terminals.clone();
+		terminals.sort();
+		terminals.dedup();
+
+		let mut distances: Vec<Option<f64>> = vec![None; self.num_nodes()];
+		let mut regions = vec![0; self.num_nodes()];
+		let mut settled = vec![false; self.num_nodes()];
+		let mut traceback_arcs: Vec<Option<(usize, usize)>> = vec![None; self.num_nodes()];
+
+		let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+		for (region, &terminal) in terminals.iter().enumerate() {
+			distances[terminal] = Some(0.0);
+			regions[terminal] = region;
+			active_nodes.push((0, terminal));
+		}
+
+		while let Some((_, node)) = active_nodes.pop() {
+			if settled[node] {
+				continue;
+			}
+
+			settled[node] = true;
+
+			let node_distance = distances[node].unwrap();
+			for (next_node, arc) in self.undirected_arcs(node) {
+				if settled[next_node] {
+					continue;
+				}
+
+				let next_distance = node_distance + self.arc_costs[arc];
+				match distances[next_node] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+
+				distances[next_node] = Some(next_distance);
+				regions[next_node] = regions[node];
+				traceback_arcs[next_node] = Some((node, arc));
+
+				// Use negative numbers since we only have a max-heap
+				active_nodes.push((-(next_distance * 1000.0) as i64, next_node));
+			}
+		}
+
+		// Arcs between regions connect their terminals by a path through the arc
+		let mut bridges = Vec::new();
+		for node in (0..self.num_nodes()).filter(|&node| settled[node]) {
+			for arc in self.arcs(node) {
+				let head_node = self.arc_heads[arc];
+				if settled[head_node] && regions[node] != regions[head_node] {
+					let costs = distances[node].unwrap() + self.arc_costs[arc] + distances[head_node].unwrap();
+					bridges.push((costs, arc, node, head_node));
+				}
+			}
+		}
+		bridges.sort_by(|first, second| (first.0, first.1).partial_cmp(&(second.0, second.1)).unwrap());
+
+		let mut terminal_sets = UnionFind::new(terminals.len());
+		let mut path_arcs = HashSet::new();
+		let mut num_connected = 1;
+		for &(_, arc, tail_node, head_node) in bridges.iter() {
+			if !terminal_sets.union(regions[tail_node], regions[head_node]) {
+				continue;
+			}
+
+			num_connected += 1;
+			path_arcs.insert(arc);
+			for &end_node in [tail_node, head_node].iter() {
+				let mut node = end_node;
+				while let Some((previous_node, arc)) = traceback_arcs[node] {
+					path_arcs.insert(arc);
+					node = previous_node;
+				}
+			}
+		}
+
+		if terminals.len() > 1 && num_connected < terminals.len() {
+			return None;
+		}
+
+		// Paths may share nodes, so the spanning tree of their arcs is smaller
+		let mut arcs: Vec<usize> = path_arcs.into_iter().collect();
+		arcs.sort_by(|&first, &second| (self.arc_costs[first], first).partial_cmp(&(self.arc_costs[second], second)).unwrap());
+		let mut arcs = self.select_forest_arcs(arcs);
+		self.prune_leaves(&mut arcs, &terminals);
+
+		return Some(SpanningTree::new(self, terminals, arcs));
+	}
+
+	/// Returns the arcs in the given order which do not close a cycle.
+	fn select_forest_arcs(&self, arcs: Vec<usize>) -> Vec<usize> {
+		let mut components = UnionFind::new(self.num_nodes());
+
+		arcs.into_iter()
+			.filter(|&arc| components.union(self.arc_tail(arc), self.arc_heads[arc]))
+			.collect()
+	}
+
+	/// Remove the arcs leading to leaves of the tree which are no terminals, until all leaves are terminals.
+	fn prune_leaves(&self, arcs: &mut Vec<usize>, terminals: &Vec<usize>) {
+		let mut is_terminal = vec![false; self.num_nodes()];
+		for &terminal in terminals.iter() {
+			is_terminal[terminal] = true;
+		}
+
+		let mut incident_arcs: Vec<Vec<usize>> = vec![Vec::new(); self.num_nodes()];
+		for &arc in arcs.iter() {
+			incident_arcs[self.arc_tail(arc)].push(arc);
+			incident_arcs[self.arc_heads[arc]].push(arc);
+		}
+
+		let mut removed = HashSet::new();
+		let mut leaves: Vec<usize> = (0..self.num_nodes())
+			.filter(|&node| incident_arcs[node].len() == 1 && !is_terminal[node])
+			.collect();
+
+		while let Some(leaf) = leaves.pop() {
+			let arc = match incident_arcs[leaf].pop() {
+				Some(arc) => arc,
+				None => { continue; }
+			};
+
+			removed.insert(arc);
+			let other_node = match self.arc_heads[arc] == leaf {
+				true => self.arc_tail(arc),
+				false => self.arc_heads[arc]
+			};
+
+			incident_arcs[other_node].retain(|&other| other != arc);
+			if incident_arcs[other_node].len() == 1 && !is_terminal[other_node] {
+				leaves.push(other_node);
+			}
+		}
+
+		arcs.retain(|arc| !removed.contains(arc));
+	}
+
+	/// Returns the neighbours of the node over outgoing and incoming arcs with the connecting arc.
+	fn undirected_arcs(&self, node: usize) -> Vec<(usize, usize)> {
+		self.arcs(node).map(|arc| (self.arc_heads[arc], arc))
+			.chain(self.reverse_arcs(node).map(|index| (self.reverse_arc_tails[index], self.reverse_arc_ids[index])))
+			.collect()
+	}
+
+	/// Add the arcs from the node to nodes outside the tree to the heap of Prim's algorithm.
+	fn push_undirected_arcs(&self, node: usize, in_tree: &Vec<bool>, active_arcs: &mut BinaryHeap<(i64, usize, usize)>) {
+		for (next_node, arc) in self.undirected_arcs(node) {
+			if !in_tree[next_node] {
+				// Use negative numbers since we only have a max-heap
+				active_arcs.push((-(self.arc_costs[arc] * 1000.0) as i64, next_node, arc));
+			}
+		}
+	}
+}
+
+#[test]
+fn test_spanning_forest() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let kruskal = graph.compute_spanning_forest_kruskal();
+	let prim = graph.compute_spanning_forest_prim();
+
+	// Node 6 is only connected by its arc to node 5
+	assert_eq!(6, kruskal.arcs().len());
+	assert_eq!(6, prim.arcs().len());
+	assert_eq!(1400.0 * 5.0 + 5000.0, kruskal.costs());
+	assert_eq!(kruskal.costs(), prim.costs());
+	assert_eq!(&vec![0], kruskal.roots());
+	assert_eq!((0..7).collect::<Vec<usize>>(), *kruskal.nodes());
+
+	let graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+	let kruskal = graph.compute_spanning_forest_kruskal();
+	assert_eq!(30.0 + 20.0 + 40.0 + 20.0, kruskal.costs());
+	assert_eq!(Some((1, 4)), kruskal.parent(3));
+
+	// Every arc is part of exactly one branch
+	let branches = kruskal.branches();
+	assert_eq!(4, branches.iter().map(|branch| branch.len() - 1).sum::<usize>());
+	assert!(branches.iter().all(|branch| kruskal.parent(branch[0]).is_some()));
+}
+
+#[test]
+fn test_spanning_forest_components() {
+	let graph = Graph::read_graph_from_file("graphs/test2.zip").unwrap();
+
+	let kruskal = graph.compute_spanning_forest_kruskal();
+	let prim = graph.compute_spanning_forest_prim();
+	assert_eq!(kruskal.costs(), prim.costs());
+	assert_eq!(kruskal.roots().len(), prim.roots().len());
+	assert_eq!(graph.num_nodes() - kruskal.roots().len(), kruskal.arcs().len());
+
+	// No arcs between nodes 0, 1 and nodes 5, 6 of test3
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let (graph, _) = graph.extract_subgraph(&vec![0, 1, 5, 6]).unwrap();
+
+	let kruskal = graph.compute_spanning_forest_kruskal();
+	let prim = graph.compute_spanning_forest_prim();
+	assert_eq!(&vec![0, 2], kruskal.roots());
+	assert_eq!(kruskal.roots(), prim.roots());
+	assert_eq!(1600.0 + 5000.0, kruskal.costs());
+	assert_eq!(kruskal.costs(), prim.costs());
+	assert_eq!(2, kruskal.arcs().len());
+}
+
+#[test]
+fn test_steiner_tree() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	// Shortest path between two terminals
+	let tree = graph.compute_steiner_tree(&vec![0, 5]).unwrap();
+	assert_eq!(3650.0, tree.costs());
+	assert_eq!(&vec![0, 2, 5], tree.nodes());
+
+	// Node 2 connects node 1 to the others
+	let tree = graph.compute_steiner_tree(&vec![5, 1, 3]).unwrap();
+	assert_eq!(3.0 * 1400.0, tree.costs());
+	assert_eq!(&vec![1, 2, 3, 5], tree.nodes());
+	assert_eq!(&vec![1], tree.roots());
+
+	let mut mapbb = String::new();
+	tree.generate_mapbb(&graph, &mut mapbb);
+	assert_eq!("[map]48.0300,7.8100 48.0000,7.8200[/map]\n", mapbb);
+
+	// Direct arcs are cheaper than the way over node 2
+	assert_eq!(1600.0 + 2300.0 + 1400.0, graph.compute_steiner_tree(&vec![0, 1, 4, 5]).unwrap().costs());
+
+	let tree = graph.compute_steiner_tree(&vec![3, 3]).unwrap();
+	assert_eq!((0.0, &vec![3]), (tree.costs(), tree.nodes()));
+	assert!(tree.branches().is_empty());
+
+	// Node 6 can be reached over the arc to node 5 in reverse direction
+	assert_eq!(5000.0, graph.compute_steiner_tree(&vec![5, 6]).unwrap().costs());
+
+	// Terminals in different components can't be connected
+	let (graph, _) = graph.extract_subgraph(&vec![0, 1, 5, 6]).unwrap();
+	let forest = graph.compute_spanning_forest_kruskal();
+	assert_eq!(2, forest.roots().len());
+	assert!(graph.compute_steiner_tree(forest.roots()).is_none());
+	assert_eq!(1600.0, graph.compute_steiner_tree(&vec![0, 1]).unwrap().costs());
+}
\ No newline at end of file
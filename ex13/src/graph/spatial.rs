@@ -0,0 +1,230 @@
+use super::{Graph, EARTH_RADIUS};
+
+use std::f64;
+use std::vec::Vec;
+
+/// A k-d tree over the node positions for nearest node queries.
+///
+/// The positions are stored as points on the unit sphere, where the
+/// straight line distance grows with the great circle distance. The tree
+/// is implicit: the node in the middle of each range splits the range
+/// along the axis of its depth.
+pub struct SpatialIndex {
+	nodes: Vec<usize>,
+	points: Vec<[f64; 3]>
+}
+
+/// Returns the point on the unit sphere of the coordinates.
+fn to_point(latitude: f64, longitude: f64) -> [f64; 3] {
+	let (latitude, longitude) = (latitude.to_radians(), longitude.to_radians());
+	[latitude.cos() * longitude.cos(), latitude.cos() * longitude.sin(), latitude.sin()]
+}
+
+fn squared_distance(first: &[f64; 3], second: &[f64; 3]) -> f64 {
+	(0..3).map(|axis| (first[axis] - second[axis]).powi(2)).sum()
+}
+
+/// Convert a straight line distance on the unit sphere to meters on the earth.
+fn chord_to_meters(squared_chord: f64) -> f64 {
+	2.0 * EARTH_RADIUS * (squared_chord.sqrt() / 2.0).min(1.0).asin()
+}
+
+/// Convert meters on the earth to a straight line distance on the unit sphere.
+fn meters_to_chord(meters: f64) -> f64 {
+	2.0 * (meters.min(f64::consts::PI * EARTH_RADIUS) / (2.0 * EARTH_RADIUS)).sin()
+}
+
+impl SpatialIndex {
+
+	/// Build the index over all nodes of the graph.
+	///
+	/// Nodes without valid coordinates are left out, they can't be found.
+	pub fn new(graph: &Graph) -> SpatialIndex {
+		let mut entries: Vec<(usize, [f64; 3])> = graph.nodes.iter()
+			.enumerate()
+			.filter(|&(_, data)| data.latitude.is_finite() && data.longitude.is_finite())
+			.map(|(node, data)| (node, to_point(data.latitude, data.longitude)))
+			.collect();
+
+		let count = entries.len();
+		build(&mut entries, 0, count, 0);
+
+		SpatialIndex {
+			nodes: entries.iter().map(|&(node, _)| node).collect(),
+			points: entries.into_iter().map(|(_, point)| point).collect()
+		}
+	}
+
+	/// Returns the node closest to the coordinates and its distance in meters.
+	pub fn nearest(&self, latitude: f64, longitude: f64) -> Option<(usize, f64)> {
+		self.k_nearest(latitude, longitude, 1).into_iter().next()
+	}
+
+	/// Returns up to k nodes closest to the coordinates with their distances in meters.
+	///
+	/// The nodes are ordered by ascending distance.
+	pub fn k_nearest(&self, latitude: f64, longitude: f64, k: usize) -> Vec<(usize, f64)> {
+		let k = ::std::cmp::min(k, self.points.len());
+		if k == 0 {
+			return Vec::new();
+		}
+
+		let mut query = Query {
+			target: to_point(latitude, longitude),
+			limit: Some(k),
+			max_distance: f64::INFINITY,
+			found: Vec::with_capacity(k + 1)
+		};
+
+		self.search(&mut query, 0, self.points.len(), 0);
+		self.to_result(query.found)
+	}
+
+	/// Returns all nodes within the radius in meters around the coordinates
+	/// with their distances in meters.
+	///
+	/// The nodes are ordered by ascending distance.
+	pub fn within_radius(&self, latitude: f64, longitude: f64, radius: f64) -> Vec<(usize, f64)> {
+		let mut query = Query {
+			target: to_point(latitude, longitude),
+			limit: None,
+			max_distance: meters_to_chord(radius).powi(2),
+			found: Vec::new()
+		};
+
+		self.search(&mut query, 0, self.points.len(), 0);
+		query.found.sort_by(|first, second| first.0.total_cmp(&second.0).then(first.1.cmp(&second.1)));
+		self.to_result(query.found)
+	}
+
+	/// Add the points in the range closer than the max distance of the query.
+	fn search(&self, query: &mut Query, start: usize, end: usize, depth: usize) {
+		if start >= end {
+			return;
+		}
+
+		let middle = (start + end) / 2;
+		let axis = depth % 3;
+		query.add(squared_distance(&query.target, &self.points[middle]), middle);
+
+		// Search the side of the target first, the other only if it may contain closer points
+		let difference = query.target[axis] - self.points[middle][axis];
+		let (near, far) = match difference < 0.0 {
+			true => ((start, middle), (middle + 1, end)),
+			false => ((middle + 1, end), (start, middle))
+		};
+
+		self.search(query, near.0, near.1, depth + 1);
+		if difference * difference <= query.max_distance {
+			self.search(query, far.0, far.1, depth + 1);
+		}
+	}
+
+	fn to_result(&self, found: Vec<(f64, usize)>) -> Vec<(usize, f64)> {
+		found.into_iter()
+			.map(|(distance, position)| (self.nodes[position], chord_to_meters(distance)))
+			.collect()
+	}
+}
+
+/// State of a nearest node query, the distances are squared unit sphere distances.
+struct Query {
+	target: [f64; 3],
+	/// Maximum number of nodes to find, all nodes within the max distance without limit.
+	limit: Option<usize>,
+	max_distance: f64,
+	/// Distance and tree position of the nodes found so far.
+	found: Vec<(f64, usize)>
+}
+
+impl Query {
+	fn add(&mut self, distance: f64, position: usize) {
+		// Also skips invalid coordinates, which have no distance
+		if !(distance <= self.max_distance) {
+			return;
+		}
+
+		let limit = match self.limit {
+			Some(limit) => limit,
+			None => {
+				self.found.push((distance, position));
+				return;
+			}
+		};
+
+		let index = self.found.iter().position(|&(other, _)| other > distance).unwrap_or(self.found.len());
+		self.found.insert(index, (distance, position));
+		self.found.truncate(limit);
+
+		// Only closer nodes are of interest once we have enough nodes
+		if self.found.len() == limit {
+			self.max_distance = self.found[limit - 1].0;
+		}
+	}
+}
+
+/// Arrange the range into an implicit k-d tree.
+fn build(entries: &mut Vec<(usize, [f64; 3])>, start: usize, end: usize, depth: usize) {
+	if end - start <= 1 {
+		return;
+	}
+
+	let middle = (start + end) / 2;
+	let axis = depth % 3;
+	entries[start..end].select_nth_unstable_by(middle - start,
+		|first, second| first.1[axis].total_cmp(&second.1[axis]));
+
+	build(entries, start, middle, depth + 1);
+	build(entries, middle + 1, end, depth + 1);
+}
+
+#[test]
+fn test_spatial_nearest() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let index = SpatialIndex::new(&graph);
+
+	for node in 0..graph.num_nodes() {
+		let (nearest, distance) = index.nearest(graph.nodes[node].latitude, graph.nodes[node].longitude).unwrap();
+		assert_eq!(node, nearest);
+		assert!(distance < 1e-3);
+	}
+
+	let (nearest, distance) = index.nearest(48.001, 7.801).unwrap();
+	assert_eq!(0, nearest);
+	assert!((distance - graph.nodes[0].great_circle_distance(&super::Node {
+		id: 0, latitude: 48.001, longitude: 7.801 })).abs() < 1e-3);
+}
+
+#[test]
+fn test_spatial_k_nearest() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let index = SpatialIndex::new(&graph);
+
+	let nodes: Vec<usize> = index.k_nearest(48.0, 7.812, 3).iter().map(|&(node, _)| node).collect();
+	assert_eq!(vec![1, 0, 2], nodes);
+	assert_eq!(graph.num_nodes(), index.k_nearest(48.0, 7.812, 20).len());
+	assert!(index.k_nearest(48.0, 7.812, 0).is_empty());
+
+	let nodes: Vec<usize> = index.within_radius(48.0, 7.812, 1000.0).iter().map(|&(node, _)| node).collect();
+	assert_eq!(vec![1, 0], nodes);
+	assert!(index.within_radius(48.0, 7.812, 100.0).is_empty());
+
+	// Without limit all nodes are found, still ordered by distance
+	let nearest = index.k_nearest(48.0, 7.812, usize::MAX);
+	assert_eq!(graph.num_nodes(), nearest.len());
+	assert!(nearest.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+}
+
+#[test]
+fn test_spatial_invalid_coordinates() {
+	use super::LoadMode;
+
+	let text = "2\n0\n0 48.0 7.8\n1 NaN NaN\n";
+	let graph = Graph::read_graph_from_reader(text.as_bytes(), LoadMode::Directed, |_| { }).unwrap();
+	let index = SpatialIndex::new(&graph);
+
+	// Nodes and queries without valid coordinates have no distance
+	assert_eq!(vec![0], index.k_nearest(48.0, 7.8, 2).iter().map(|&(node, _)| node).collect::<Vec<usize>>());
+	assert!(index.nearest(f64::NAN, f64::NAN).is_none());
+	assert!(index.within_radius(f64::NAN, f64::NAN, 1000.0).is_empty());
+}
\ No newline at end of file
@@ -0,0 +1,204 @@
+use super::Graph;
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::vec::Vec;
+
+/// Upper bounds in meters of the arc length classes, the last class has no bound.
+const ARC_LENGTH_CLASSES: [u64; 6] = [10, 50, 100, 500, 1000, 5000];
+/// Number of component sizes shown in the report.
+const REPORTED_COMPONENTS: usize = 10;
+
+/// Distribution of the nodes and arcs of a graph.
+pub struct Statistics {
+	num_nodes: usize,
+	num_arcs: usize,
+	/// Number of nodes for each number of outgoing arcs.
+	out_degrees: Vec<usize>,
+	/// Number of nodes for each number of incoming arcs.
+	in_degrees: Vec<usize>,
+	/// Sizes of the strongly connected components in descending order.
+	component_sizes: Vec<usize>,
+	/// Number of arcs for each max speed.
+	speeds: BTreeMap<u64, usize>,
+	bounding_box: Option<(f64, f64, f64, f64)>,
+	/// Number of arcs in each length class.
+	arc_lengths: Vec<usize>,
+	/// Minimum, median, mean and maximum arc length.
+	arc_length_summary: Option<(u64, u64, f64, u64)>
+}
+
+impl Statistics {
+
+	/// Returns the number of nodes for each number of outgoing arcs.
+	pub fn out_degrees(&self) -> &Vec<usize> {
+		&self.out_degrees
+	}
+
+	/// Returns the number of nodes for each number of incoming arcs.
+	pub fn in_degrees(&self) -> &Vec<usize> {
+		&self.in_degrees
+	}
+
+	/// Returns the sizes of the strongly connected components in descending order.
+	pub fn component_sizes(&self) -> &Vec<usize> {
+		&self.component_sizes
+	}
+
+	/// Returns the number of arcs for each max speed in km/h.
+	pub fn speeds(&self) -> &BTreeMap<u64, usize> {
+		&self.speeds
+	}
+
+	/// Returns the bounding box of the nodes as min latitude, min longitude,
+	/// max latitude and max longitude, or None for an empty graph.
+	pub fn bounding_box(&self) -> Option<(f64, f64, f64, f64)> {
+		self.bounding_box
+	}
+
+	/// Returns the number of arcs in each length class.
+	///
+	/// The classes end at the bounds of the arc length classes,
+	/// the last class contains all longer arcs.
+	pub fn arc_lengths(&self) -> &Vec<usize> {
+		&self.arc_lengths
+	}
+
+	/// Returns the minimum, median, mean and maximum arc length in meters,
+	/// or None for a graph without arcs.
+	pub fn arc_length_summary(&self) -> Option<(u64, u64, f64, u64)> {
+		self.arc_length_summary
+	}
+}
+
+impl Display for Statistics {
+	fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+		try!(write!(fmt, "Nodes: {}\n", self.num_nodes));
+		try!(write!(fmt, "Arcs: {}\n", self.num_arcs));
+
+		if let Some((min_latitude, min_longitude, max_latitude, max_longitude)) = self.bounding_box {
+			try!(write!(fmt, "Bounding box: {},{},{},{}\n", min_latitude, min_longitude, max_latitude, max_longitude));
+		}
+
+		for &(name, degrees) in [("Out degrees", &self.out_degrees), ("In degrees", &self.in_degrees)].iter() {
+			try!(write!(fmt, "{}:\n", name));
+			for (degree, &count) in degrees.iter().enumerate().filter(|&(_, &count)| count > 0) {
+				try!(write!(fmt, "  {:>5}: {} nodes\n", degree, count));
+			}
+		}
+
+		let single_nodes = self.component_sizes.iter().filter(|&&size| size == 1).count();
+		try!(write!(fmt, "Strongly connected components: {} ({} of a single node)\n",
+			self.component_sizes.len(), single_nodes));
+		for (index, size) in self.component_sizes.iter().take(REPORTED_COMPONENTS).enumerate() {
+			try!(write!(fmt, "  {:>5}: {} nodes\n", index + 1, size));
+		}
+
+		try!(write!(fmt, "Max speeds:\n"));
+		for (speed, count) in self.speeds.iter() {
+			try!(write!(fmt, "  {:>3} km/h: {} arcs\n", speed, count));
+		}
+
+		try!(write!(fmt, "Arc lengths:\n"));
+		for (class, &count) in self.arc_lengths.iter().enumerate() {
+			match (class, ARC_LENGTH_CLASSES.get(class)) {
+				(0, Some(bound)) => { try!(write!(fmt, "  < {} m: {} arcs\n", bound, count)); },
+				(_, Some(bound)) => { try!(write!(fmt, "  {} - {} m: {} arcs\n", ARC_LENGTH_CLASSES[class - 1], bound, count)); },
+				(_, None) => { try!(write!(fmt, "  >= {} m: {} arcs\n", ARC_LENGTH_CLASSES[class - 1], count)); }
+			}
+		}
+
+		if let Some((min, median, mean, max)) = self.arc_length_summary {
+			try!(write!(fmt, "  min {} m, median {} m, mean {:.1} m, max {} m\n", min, median, mean, max));
+		}
+
+		Ok(())
+	}
+}
+
+impl Graph {
+
+	/// Compute the degree distribution, component sizes, speeds, bounding box
+	/// and arc lengths of the graph.
+	pub fn compute_statistics(&self) -> Statistics {
+		let mut out_degrees = Vec::new();
+		let mut in_degrees = Vec::new();
+		for node in 0..self.num_nodes() {
+			count_value(&mut out_degrees, self.arcs(node).len());
+			count_value(&mut in_degrees, self.reverse_arcs(node).len());
+		}
+
+		let mut component_sizes = self.compute_strongly_connected_components().sizes().clone();
+		component_sizes.sort_by(|first, second| second.cmp(first));
+
+		let mut speeds = BTreeMap::new();
+		for &speed in self.arc_max_speeds.iter() {
+			*speeds.entry(speed).or_insert(0) += 1;
+		}
+
+		let bounding_box = self.nodes.iter().fold(None, |bounding_box, node| {
+			Some(match bounding_box {
+				Some((min_latitude, min_longitude, max_latitude, max_longitude)) =>
+					(node.latitude.min(min_latitude), node.longitude.min(min_longitude),
+						node.latitude.max(max_latitude), node.longitude.max(max_longitude)),
+				None => (node.latitude, node.longitude, node.latitude, node.longitude)
+			})
+		});
+
+		let mut arc_lengths = vec![0; ARC_LENGTH_CLASSES.len() + 1];
+		for &distance in self.arc_distances.iter() {
+			let class = ARC_LENGTH_CLASSES.iter().position(|&bound| distance < bound).unwrap_or(ARC_LENGTH_CLASSES.len());
+			arc_lengths[class] += 1;
+		}
+
+		let mut distances = self.arc_distances.clone();
+		distances.sort();
+		let arc_length_summary = match distances.is_empty() {
+			true => None,
+			false => {
+				let mean = distances.iter().sum::<u64>() as f64 / distances.len() as f64;
+				Some((distances[0], distances[distances.len() / 2], mean, distances[distances.len() - 1]))
+			}
+		};
+
+		Statistics {
+			num_nodes: self.num_nodes(),
+			num_arcs: self.num_arcs(),
+			out_degrees: out_degrees,
+			in_degrees: in_degrees,
+			component_sizes: component_sizes,
+			speeds: speeds,
+			bounding_box: bounding_box,
+			arc_lengths: arc_lengths,
+			arc_length_summary: arc_length_summary
+		}
+	}
+}
+
+/// Increment the count of the value, growing the counts as needed.
+fn count_value(counts: &mut Vec<usize>, value: usize) {
+	if counts.len() <= value {
+		counts.resize(value + 1, 0);
+	}
+	counts[value] += 1;
+}
+
+#[test]
+fn test_statistics() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let statistics = graph.compute_statistics();
+
+	// Node 6 has a single arc and no incoming arcs
+	assert_eq!(&vec![0, 1, 1, 3, 1, 1], statistics.out_degrees());
+	assert_eq!(&vec![1, 0, 0, 3, 3], statistics.in_degrees());
+	assert_eq!(&vec![6, 1], statistics.component_sizes());
+	assert_eq!(vec![(30, 9), (50, 6), (100, 6)], statistics.speeds().iter().map(|(&speed, &count)| (speed, count)).collect::<Vec<(u64, usize)>>());
+	assert_eq!(Some((48.0, 7.8, 48.05, 7.85)), statistics.bounding_box());
+	assert_eq!(&vec![0, 0, 0, 0, 0, 20, 1], statistics.arc_lengths());
+	assert_eq!(Some((1400, 1400, 37950.0 / 21.0, 5000)), statistics.arc_length_summary());
+
+	let report = statistics.to_string();
+	assert!(report.starts_with("Nodes: 7\nArcs: 21\nBounding box: 48,7.8,48.05,7.85\n"));
+	assert!(report.contains("Strongly connected components: 2 (1 of a single node)\n"));
+	assert!(report.contains("  >= 5000 m: 1 arcs\n"));
+}
\ No newline at end of file
@@ -0,0 +1,158 @@
+use super::{Error, Graph, Node};
+
+use std::collections::HashMap;
+use std::result::Result;
+use std::vec::Vec;
+
+/// Mapping between the nodes of a graph and the nodes of a subgraph.
+pub struct NodeMapping {
+	old_nodes: Box<Vec<usize>>,
+	new_nodes: Vec<Option<usize>>
+}
+
+impl NodeMapping {
+
+	/// Returns the subgraph node of the graph node if it is in the subgraph.
+	pub fn new_node(&self, old_node: usize) -> Option<usize> {
+		self.new_nodes[old_node]
+	}
+
+	/// Returns the graph node of the subgraph node.
+	pub fn old_node(&self, new_node: usize) -> usize {
+		self.old_nodes[new_node]
+	}
+
+	/// Returns the graph nodes of all subgraph nodes.
+	pub fn old_nodes(&self) -> &Vec<usize> {
+		&self.old_nodes
+	}
+}
+
+impl Graph {
+
+	/// Extract the given nodes and the arcs between them as a new graph.
+	///
+	/// The nodes are renumbered from zero in the given order,
+	/// the arc costs of the new graph are the distances.
+	pub fn extract_subgraph(&self, nodes: &Vec<usize>) -> Result<(Graph, NodeMapping), Error> {
+		let mut contained = vec![false; self.num_nodes()];
+
+		for &node in nodes.iter() {
+			if node >= self.num_nodes() {
+				return Err(Error::from(format!("Invalid subgraph! (Unknown node {})", node)));
+			}
+			if contained[node] {
+				return Err(Error::from(format!("Invalid subgraph! (Duplicate node {})", node)));
+			}
+
+			contained[node] = true;
+		}
+
+		Ok(self.extract_nodes(nodes))
+	}
+
+	/// Extract the nodes with the given ids and the arcs between them as a new graph.
+	///
+	/// The ids are the node ids of the graph file,
+	/// the nodes are renumbered like in extract_subgraph.
+	pub fn extract_node_ids(&self, ids: &Vec<usize>) -> Result<(Graph, NodeMapping), Error> {
+		let nodes_by_id: HashMap<usize, usize> = self.nodes.iter()
+			.enumerate()
+			.map(|(node, data)| (data.id, node))
+			.collect();
+
+		let mut nodes = Vec::with_capacity(ids.len());
+		for id in ids.iter() {
+			match nodes_by_id.get(id) {
+				Some(&node) => { nodes.push(node); },
+				None => { return Err(Error::from(format!("Invalid subgraph! (Unknown node id {})", id))); }
+			}
+		}
+
+		self.extract_subgraph(&nodes)
+	}
+
+	/// Extract the nodes inside the bounding box and the arcs between them as a new graph.
+	///
+	/// The nodes keep their order but are renumbered from zero.
+	pub fn extract_bounding_box(&self, min_latitude: f64, min_longitude: f64,
+			max_latitude: f64, max_longitude: f64) -> (Graph, NodeMapping) {
+		let nodes = (0..self.num_nodes())
+			.filter(|&node| {
+				let data = &self.nodes[node];
+				data.latitude >= min_latitude && data.latitude <= max_latitude
+					&& data.longitude >= min_longitude && data.longitude <= max_longitude
+			})
+			.collect();
+
+		self.extract_nodes(&nodes)
+	}
+
+	/// Build the graph of the given distinct nodes and the arcs between them.
+	pub(super) fn extract_nodes(&self, nodes: &Vec<usize>) -> (Graph, NodeMapping) {
+		let mut new_nodes = vec![None; self.num_nodes()];
+		for (new_node, &node) in nodes.iter().enumerate() {
+			new_nodes[node] = Some(new_node);
+		}
+
+		let mut graph = Graph::new();
+		let mut arcs = Vec::new();
+
+		for (new_node, &node) in nodes.iter().enumerate() {
+			graph.nodes.push(Node {
+				id: new_node,
+				latitude: self.nodes[node].latitude,
+				longitude: self.nodes[node].longitude
+			});
+
+			for arc in self.arcs(node) {
+				if let Some(head_node) = new_nodes[self.arc_heads[arc]] {
+					arcs.push((new_node, head_node, self.arc_distances[arc], self.arc_max_speeds[arc]));
+				}
+			}
+		}
+
+		graph.build_arcs(arcs);
+
+		let mapping = NodeMapping {
+			old_nodes: Box::new(nodes.clone()),
+			new_nodes: new_nodes
+		};
+
+		return (graph, mapping);
+	}
+}
+
+#[test]
+fn test_extract_subgraph() {
+	let graph = Graph::read_graph_from_file("graphs/test.zip").unwrap();
+
+	let (subgraph, mapping) = graph.extract_subgraph(&vec![3, 1]).unwrap();
+	assert_eq!("[0->1(40)]", format!("{}", subgraph));
+	assert_eq!(vec![3, 1], *mapping.old_nodes());
+	assert_eq!(Some(1), mapping.new_node(1));
+	assert_eq!(None, mapping.new_node(2));
+	assert_eq!(3, mapping.old_node(0));
+
+	assert!(graph.extract_subgraph(&vec![1, 5]).is_err());
+	assert!(graph.extract_subgraph(&vec![1, 1]).is_err());
+
+	let (subgraph, _) = graph.extract_node_ids(&vec![4, 3]).unwrap();
+	assert_eq!("[0->1(20)]", format!("{}", subgraph));
+	assert!(graph.extract_node_ids(&vec![9]).is_err());
+}
+
+#[test]
+fn test_extract_bounding_box() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+
+	let (subgraph, mapping) = graph.extract_bounding_box(47.99, 7.79, 48.015, 7.83);
+	assert_eq!(vec![0, 1, 2], *mapping.old_nodes());
+	assert_eq!("[0->1(1600), 0->2(1400), 1->0(1600), 1->2(1400), 2->0(1400), 2->1(1400)]",
+		format!("{}", subgraph));
+	assert_eq!(graph.nodes[2].longitude, subgraph.nodes[2].longitude);
+
+	let (subgraph, mapping) = graph.extract_bounding_box(50.0, 7.0, 51.0, 8.0);
+	assert_eq!(0, subgraph.num_nodes());
+	assert_eq!(None, mapping.new_node(0));
+}
\ No newline at end of file
@@ -0,0 +1,385 @@
+use super::{Error, Graph};
+
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::result::Result;
+use std::vec::Vec;
+
+/// Length of the period of the travel time profiles in seconds.
+pub const DAY: f64 = 86400.0;
+
+/// Maximum number of departures sampled for an earliest arrival profile.
+pub const MAX_PROFILE_DEPARTURES: usize = 10000;
+
+/// Travel time of an arc over the day as a piecewise-linear function.
+///
+/// The function repeats every day and interpolates linearly between its
+/// points, also from the last point of a day to the first of the next day.
+/// Departing later never results in an earlier arrival (FIFO property).
+#[derive(Clone)]
+pub struct TravelTimeProfile {
+	/// Departure time of day and travel time in seconds, sorted by departure.
+	points: Vec<(f64, f64)>
+}
+
+impl TravelTimeProfile {
+
+	/// Create a profile from departure time of day and travel time pairs in seconds.
+	pub fn new(mut points: Vec<(f64, f64)>) -> Result<TravelTimeProfile, Error> {
+		if points.is_empty() {
+			return Err(Error::from("Invalid travel time profile! (No points)"));
+		}
+
+		for &(departure, travel_time) in points.iter() {
+			if !(departure >= 0.0 && departure < DAY) || !(travel_time >= 0.0) {
+				return Err(Error::from(format!("Invalid travel time profile! (Invalid point {}, {})",
+					departure, travel_time)));
+			}
+		}
+
+		points.sort_by(|first, second| first.partial_cmp(second).unwrap());
+
+		// The arrival time may not decrease between two points
+		for index in 0..points.len() {
+			let (departure, travel_time) = points[index];
+			let (next_departure, next_travel_time) = match index + 1 < points.len() {
+				true => points[index + 1],
+				false => (points[0].0 + DAY, points[0].1)
+			};
+
+			if next_departure == departure && index + 1 < points.len() {
+				return Err(Error::from(format!("Invalid travel time profile! (Duplicate departure {})", departure)));
+			}
+			if next_departure + next_travel_time < departure + travel_time {
+				return Err(Error::from(format!("Invalid travel time profile! (Not FIFO after departure {})", departure)));
+			}
+		}
+
+		Ok(TravelTimeProfile { points: points })
+	}
+
+	/// Create a profile with the same travel time for the whole day.
+	pub fn constant(travel_time: f64) -> TravelTimeProfile {
+		TravelTimeProfile { points: vec![(0.0, travel_time)] }
+	}
+
+	/// Returns the travel time in seconds when departing at the given time.
+	///
+	/// The departure time is given in seconds and may span multiple days.
+	pub fn travel_time(&self, departure: f64) -> f64 {
+		let time_of_day = departure - (departure / DAY).floor() * DAY;
+
+		// Index of the first point after the time of day
+		let next = match self.points.binary_search_by(|point| point.0.partial_cmp(&time_of_day).unwrap()) {
+			Ok(index) => { return self.points[index].1; },
+			Err(index) => index
+		};
+
+		let (previous_departure, previous_travel_time) = match next {
+			0 => (self.points[self.points.len() - 1].0 - DAY, self.points[self.points.len() - 1].1),
+			_ => self.points[next - 1]
+		};
+		let (next_departure, next_travel_time) = match next == self.points.len() {
+			true => (self.points[0].0 + DAY, self.points[0].1),
+			false => self.points[next]
+		};
+
+		if next_departure == previous_departure {
+			return previous_travel_time;
+		}
+
+		let ratio = (time_of_day - previous_departure) / (next_departure - previous_departure);
+		previous_travel_time + ratio * (next_travel_time - previous_travel_time)
+	}
+
+	/// Returns the arrival time in seconds when departing at the given time.
+	pub fn arrival(&self, departure: f64) -> f64 {
+		departure + self.travel_time(departure)
+	}
+}
+
+/// Time-dependent travel times of all arcs of a graph.
+///
+/// Arcs without profile use the static travel time with the max speed.
+/// The vehicle is never faster than its max speed on any arc.
+pub struct Traffic {
+	max_speed: u64,
+	arc_profiles: Vec<Option<usize>>,
+	profiles: Vec<TravelTimeProfile>
+}
+
+impl Traffic {
+
+	/// Create the travel times without any profiles.
+	pub fn new(graph: &Graph, max_speed: u64) -> Traffic {
+		Traffic {
+			max_speed: max_speed,
+			arc_profiles: vec![None; graph.num_arcs()],
+			profiles: Vec::new()
+		}
+	}
+
+	/// Read the profiles of the graph from a text file.
+	///
+	/// Each line has the form "TAIL HEAD HH:MM=SECONDS ..." and sets the
+	/// travel time profile of all arcs from node TAIL to node HEAD. Nodes
+	/// are given by their ids.
+	pub fn read_from_file(graph: &Graph, name: &str, max_speed: u64) -> Result<Traffic, Error> {
+		let buf = BufReader::new(try!(File::open(name)));
+		let mut traffic = Traffic::new(graph, max_speed);
+
+		for (line_number, line_res) in buf.lines().enumerate() {
+			let line = try!(line_res);
+			let line = line.trim();
+
+			if line.starts_with("#") || line.is_empty() {
+				continue;
+			}
+
+			let parts: Vec<&str> = line.split_whitespace().collect();
+			if parts.len() < 3 {
+				return Err(Error::from(format!("Invalid traffic file! (Missing points, line {})", line_number + 1)));
+			}
+
+			let tail_node = graph.node_with_id(try!(parts[0].parse::<usize>()));
+			let head_node = graph.node_with_id(try!(parts[1].parse::<usize>()));
+			let (tail_node, head_node) = match (tail_node, head_node) {
+				(Some(tail_node), Some(head_node)) => (tail_node, head_node),
+				_ => { return Err(Error::from(format!("Invalid traffic file! (Unknown node, line {})", line_number + 1))); }
+			};
+
+			let mut points = Vec::with_capacity(parts.len() - 2);
+			for part in parts[2..].iter() {
+				let mut point = part.splitn(2, '=');
+				let departure = point.next().and_then(parse_time_of_day);
+				let travel_time = point.next().and_then(|value| value.parse::<f64>().ok());
+				match (departure, travel_time) {
+					(Some(departure), Some(travel_time)) => { points.push((departure, travel_time)); },
+					_ => { return Err(Error::from(format!("Invalid traffic file! (Invalid point, line {})", line_number + 1))); }
+				}
+			}
+
+			let profile = match TravelTimeProfile::new(points) {
+				Ok(profile) => profile,
+				Err(error) => { return Err(Error::from(format!("{} (line {})", error, line_number + 1))); }
+			};
+
+			let arcs: Vec<usize> = graph.arcs(tail_node).filter(|&arc| graph.arc_heads[arc] == head_node).collect();
+			if arcs.is_empty() {
+				return Err(Error::from(format!("Invalid traffic file! (Unknown arc, line {})", line_number + 1)));
+			}
+
+			for &arc in arcs.iter() {
+				traffic.set_profile(arc, profile.clone());
+			}
+		}
+
+		Ok(traffic)
+	}
+
+	/// Set the travel time profile of the arc.
+	pub fn set_profile(&mut self, arc: usize, profile: TravelTimeProfile) {
+		self.arc_profiles[arc] = Some(self.profiles.len());
+		self.profiles.push(profile);
+	}
+
+	/// Returns the time of arrival at the head of the arc when departing at the given time.
+	pub fn arrival(&self, graph: &Graph, arc: usize, departure: f64) -> f64 {
+		let travel_time = graph.arc_travel_time(arc, self.max_speed);
+		match self.arc_profiles[arc] {
+			Some(profile) => departure + self.profiles[profile].travel_time(departure).max(travel_time),
+			None => departure + travel_time
+		}
+	}
+}
+
+/// Parse a time of day in the format HH:MM or HH:MM:SS to seconds.
+pub fn parse_time_of_day(value: &str) -> Option<f64> {
+	let parts: Vec<&str> = value.split(':').collect();
+	if parts.len() < 2 || parts.len() > 3 {
+		return None;
+	}
+
+	let mut seconds = 0.0;
+	for (index, part) in parts.iter().enumerate() {
+		match part.parse::<u32>() {
+			Ok(value) if index == 0 || value < 60 => { seconds = seconds * 60.0 + value as f64; },
+			_ => { return None; }
+		}
+	}
+
+	match parts.len() {
+		2 => Some(seconds * 60.0),
+		_ => Some(seconds)
+	}
+}
+
+impl Graph {
+
+	/// Compute the earliest arrival at the end node when departing at the given time.
+	///
+	/// Runs a time-dependent Dijkstra search, which is exact since all
+	/// travel times are FIFO. The result is a tuple of the arrival time in
+	/// seconds, the nodes on the path and the number of settled nodes.
+	pub fn compute_earliest_arrival(&self, start_node: usize, end_node: usize, departure: f64,
+			traffic: &Traffic) -> Option<(f64, Box<Vec<usize>>, usize)> {
+		let node_count = self.num_nodes();
+		let mut arrivals: Vec<Option<f64>> = vec![None; node_count];
+		let mut settled = vec![false; node_count];
+		let mut traceback_nodes: Vec<Option<usize>> = vec![None; node_count];
+		let mut num_settled = 0;
+
+		let mut active_nodes = BinaryHeap::<(i64, usize)>::new();
+		arrivals[start_node] = Some(departure);
+		active_nodes.push((-(departure * 1000.0) as i64, start_node));
+
+		while let Some((_, node)) = active_nodes.pop() {
+			if settled[node] {
+				continue;
+			}
+
+			settled[node] = true;
+			num_settled += 1;
+
+			if node == end_node {
+				break;
+			}
+
+			let node_arrival = arrivals[node].unwrap();
+			for arc in self.arcs(node) {
+				let head_node = self.arc_heads[arc];
+				if settled[head_node] {
+					continue;
+				}
+
+				let next_arrival = traffic.arrival(self, arc, node_arrival);
+				match arrivals[head_node] {
+					Some(arrival) if arrival <= next_arrival => { continue; },
+					_ => { }
+				}
+
+				arrivals[head_node] = Some(next_arrival);
+				traceback_nodes[head_node] = Some(node);
+
+				// Use negative numbers since we only have a max-heap
+				active_nodes.push((-(next_arrival * 1000.0) as i64, head_node));
+			}
+		}
+
+		if !settled[end_node] {
+			return None;
+		}
+
+		let mut path = Box::new(vec![end_node]);
+		let mut node = end_node;
+		while let Some(previous_node) = traceback_nodes[node] {
+			path.push(previous_node);
+			node = previous_node;
+		}
+		path.reverse();
+
+		return Some((arrivals[end_node].unwrap(), path, num_settled));
+	}
+
+	/// Sample the earliest arrivals at the end node for departures in a time window.
+	///
+	/// This runs compute_earliest_arrival for each departure, starting at the
+	/// first departure and following each other in the given interval up to
+	/// the last departure. Arrivals between the sampled departures are not
+	/// computed. The result contains the departure and arrival times in
+	/// seconds, or None if the end node can't be reached.
+	///
+	/// Fails for intervals which are not positive or which would need more
+	/// than MAX_PROFILE_DEPARTURES departures.
+	pub fn compute_earliest_arrival_profile(&self, start_node: usize, end_node: usize, first_departure: f64,
+			last_departure: f64, interval: f64, traffic: &Traffic) -> Result<Option<Vec<(f64, f64)>>, Error> {
+		if !(interval > 0.0) {
+			return Err(Error::from(format!("Invalid departure interval {}! (Not positive)", interval)));
+		}
+
+		let samples = match last_departure < first_departure {
+			true => 0.0,
+			false => ((last_departure - first_departure) / interval).floor() + 1.0
+		};
+		if !(samples <= MAX_PROFILE_DEPARTURES as f64) {
+			return Err(Error::from(format!("Invalid departure interval {}! (More than {} departures)",
+				interval, MAX_PROFILE_DEPARTURES)));
+		}
+
+		let mut profile = Vec::with_capacity(samples as usize);
+		for index in 0..samples as usize {
+			let departure = first_departure + index as f64 * interval;
+			match self.compute_earliest_arrival(start_node, end_node, departure, traffic) {
+				Some((arrival, _, _)) => { profile.push((departure, arrival)); },
+				None => { return Ok(None); }
+			}
+		}
+
+		return Ok(Some(profile));
+	}
+}
+
+#[test]
+fn test_travel_time_profile() {
+	let profile = TravelTimeProfile::new(vec![(8.0 * 3600.0, 600.0), (7.0 * 3600.0, 100.0), (9.0 * 3600.0, 100.0)]).unwrap();
+	assert_eq!(100.0, profile.travel_time(0.0));
+	assert_eq!(350.0, profile.travel_time(7.5 * 3600.0));
+	assert_eq!(600.0, profile.travel_time(8.0 * 3600.0));
+	assert_eq!(350.0, profile.travel_time(DAY + 8.5 * 3600.0));
+	assert_eq!(100.0, profile.travel_time(-3600.0));
+	assert_eq!(50.0, TravelTimeProfile::constant(50.0).travel_time(1234.0));
+
+	// Departing 50 seconds later arrives 50 seconds earlier
+	assert!(TravelTimeProfile::new(vec![(7.0 * 3600.0, 100.0), (7.0 * 3600.0 + 50.0, 0.0)]).is_err());
+	assert!(TravelTimeProfile::new(vec![(7.0 * 3600.0, 100.0), (7.0 * 3600.0, 200.0)]).is_err());
+	assert!(TravelTimeProfile::new(vec![(DAY, 100.0)]).is_err());
+	assert!(TravelTimeProfile::new(Vec::new()).is_err());
+
+	assert_eq!(Some(8.0 * 3600.0), parse_time_of_day("08:00"));
+	assert_eq!(Some(30.0 * 3600.0 + 90.0), parse_time_of_day("30:01:30"));
+	assert_eq!(None, parse_time_of_day("08:60"));
+	assert_eq!(None, parse_time_of_day("8"));
+}
+
+#[test]
+fn test_earliest_arrival() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let traffic = Traffic::read_from_file(&graph, "graphs/test3.traffic", 130).unwrap();
+
+	// Same as the fastest path without traffic
+	let mut fastest = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	fastest.set_arc_costs_to_travel_time(130);
+	let free = Traffic::new(&graph, 130);
+	for start in 0..graph.num_nodes() {
+		for end in 0..graph.num_nodes() {
+			let dijkstra = fastest.compute_shortest_path_dijkstra(start, end, 130);
+			let arrival = graph.compute_earliest_arrival(start, end, 3600.0, &free);
+			assert_eq!(dijkstra.is_some(), arrival.is_some());
+			if let (Some(dijkstra), Some(arrival)) = (dijkstra, arrival) {
+				assert!((dijkstra.1 - (arrival.0 - 3600.0)).abs() < 1e-6);
+			}
+		}
+	}
+
+	let (arrival, path, _) = graph.compute_earliest_arrival(0, 5, 6.0 * 3600.0, &traffic).unwrap();
+	assert!((arrival - (6.0 * 3600.0 + 133.2)).abs() < 1e-6);
+	assert_eq!(vec![0, 3, 5], *path);
+
+	// The arc from 0 to 3 is jammed at 08:00
+	let (arrival, path, _) = graph.compute_earliest_arrival(0, 5, 8.0 * 3600.0, &traffic).unwrap();
+	assert!((arrival - (8.0 * 3600.0 + 298.8)).abs() < 1e-6);
+	assert_eq!(vec![0, 1, 4, 5], *path);
+
+	assert!(graph.compute_earliest_arrival(5, 6, 0.0, &traffic).is_none());
+
+	let profile = graph.compute_earliest_arrival_profile(0, 5, 6.0 * 3600.0, 10.0 * 3600.0, 3600.0, &traffic).unwrap().unwrap();
+	let travel_times: Vec<i64> = profile.iter().map(|&(departure, arrival)| ((arrival - departure) * 10.0).round() as i64).collect();
+	assert_eq!(vec![1332, 1332, 2988, 1332, 1332], travel_times);
+	assert!(graph.compute_earliest_arrival_profile(5, 6, 0.0, 3600.0, 600.0, &traffic).unwrap().is_none());
+
+	// Tiny intervals would never finish
+	assert!(graph.compute_earliest_arrival_profile(0, 5, 0.0, 3600.0, 0.0, &traffic).is_err());
+	assert!(graph.compute_earliest_arrival_profile(0, 5, 6.0 * 3600.0, 10.0 * 3600.0, 1e-9, &traffic).is_err());
+	assert_eq!(Some(Vec::new()), graph.compute_earliest_arrival_profile(0, 5, 3600.0, 0.0, 600.0, &traffic).unwrap());
+}
\ No newline at end of file
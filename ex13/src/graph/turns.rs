@@ -0,0 +1,337 @@
+use super::{Error, Graph};
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::result::Result;
+use std::vec::Vec;
+
+/// Banned turns between arcs.
+///
+/// A turn is given by the arc entering and the arc leaving the via node.
+pub struct TurnRestrictions {
+	banned: HashSet<(usize, usize)>,
+	/// Arcs which only allow the given turns.
+	only: HashMap<usize, HashSet<usize>>
+}
+
+impl TurnRestrictions {
+
+	/// Create restrictions without banned turns.
+	pub fn new() -> TurnRestrictions {
+		TurnRestrictions {
+			banned: HashSet::new(),
+			only: HashMap::new()
+		}
+	}
+
+	/// Read the restrictions of the graph from a text file.
+	///
+	/// Each line has the form "no FROM VIA TO" to ban the turn from node
+	/// FROM over node VIA to node TO, or "only FROM VIA TO" to ban all other
+	/// turns from node FROM over node VIA. Nodes are given by their ids.
+	pub fn read_from_file(graph: &Graph, name: &str) -> Result<TurnRestrictions, Error> {
+		let buf = BufReader::new(try!(File::open(name)));
+		let mut restrictions = TurnRestrictions::new();
+
+		for (line_number, line_res) in buf.lines().enumerate() {
+			let line = try!(line_res);
+			let line = line.trim();
+
+			if line.starts_with("#") || line.is_empty() {
+				continue;
+			}
+
+			let parts: Vec<&str> = line.split_whitespace().collect();
+			if parts.len() != 4 {
+				return Err(Error::from(format!("Invalid restriction file! (Invalid restriction, line {})", line_number + 1)));
+			}
+
+			let mut nodes = Vec::with_capacity(3);
+			for part in parts[1..].iter() {
+				match graph.node_with_id(try!(part.parse::<usize>())) {
+					Some(node) => { nodes.push(node); },
+					None => {
+						return Err(Error::from(format!("Invalid restriction file! (Unknown node, line {})", line_number + 1)));
+					}
+				}
+			}
+
+			// Restrict all parallel arcs
+			let from_arcs: Vec<usize> = graph.arcs(nodes[0]).filter(|&arc| graph.arc_heads[arc] == nodes[1]).collect();
+			let to_arcs: Vec<usize> = graph.arcs(nodes[1]).filter(|&arc| graph.arc_heads[arc] == nodes[2]).collect();
+			if from_arcs.is_empty() || to_arcs.is_empty() {
+				return Err(Error::from(format!("Invalid restriction file! (Unknown arc, line {})", line_number + 1)));
+			}
+
+			for &from_arc in from_arcs.iter() {
+				for &to_arc in to_arcs.iter() {
+					match parts[0] {
+						"no" => { restrictions.ban(from_arc, to_arc); },
+						"only" => { restrictions.only(from_arc, to_arc); },
+						_ => {
+							return Err(Error::from(format!("Invalid restriction file! (Unknown type, line {})", line_number + 1)));
+						}
+					}
+				}
+			}
+		}
+
+		Ok(restrictions)
+	}
+
+	/// Ban the turn from the first to the second arc.
+	pub fn ban(&mut self, from_arc: usize, to_arc: usize) {
+		self.banned.insert((from_arc, to_arc));
+	}
+
+	/// Allow the turn from the first to the second arc, but ban
+	/// all other turns of the first arc which are not allowed this way.
+	pub fn only(&mut self, from_arc: usize, to_arc: usize) {
+		self.only.entry(from_arc).or_insert_with(HashSet::new).insert(to_arc);
+	}
+
+	/// Returns if the turn from the first to the second arc is allowed.
+	pub fn is_allowed(&self, from_arc: usize, to_arc: usize) -> bool {
+		if self.banned.contains(&(from_arc, to_arc)) {
+			return false;
+		}
+
+		match self.only.get(&from_arc) {
+			Some(allowed) => allowed.contains(&to_arc),
+			None => true
+		}
+	}
+}
+
+/// Costs of turns based on their angle.
+///
+/// The costs are in the unit of the arc costs, meters or seconds.
+#[derive(Clone, Copy)]
+pub struct TurnCosts {
+	/// Turns up to this angle in degrees are free.
+	pub free_angle: f64,
+	/// Costs per degree above the free angle.
+	pub cost_per_degree: f64,
+	/// Factor for the costs of left turns, which cross the oncoming traffic.
+	pub left_turn_factor: f64,
+	/// Whether turning back to the previous node is allowed.
+	pub allow_u_turns: bool
+}
+
+impl TurnCosts {
+
+	/// Turns without costs, U-turns included.
+	pub fn none() -> TurnCosts {
+		TurnCosts {
+			free_angle: 180.0,
+			cost_per_degree: 0.0,
+			left_turn_factor: 1.0,
+			allow_u_turns: true
+		}
+	}
+
+	/// Returns the costs of a turn with the given angle
+	/// or None if the turn is not allowed.
+	///
+	/// Right turns have positive, left turns negative angles.
+	pub fn cost(&self, angle: f64, u_turn: bool) -> Option<f64> {
+		if u_turn && !self.allow_u_turns {
+			return None;
+		}
+
+		let costs = (angle.abs() - self.free_angle).max(0.0) * self.cost_per_degree;
+		match angle < 0.0 {
+			true => Some(costs * self.left_turn_factor),
+			false => Some(costs)
+		}
+	}
+}
+
+impl Graph {
+
+	/// Returns the compass direction of the arc in degrees.
+	fn arc_bearing(&self, tail_node: usize, head_node: usize) -> f64 {
+		let (tail, head) = (&self.nodes[tail_node], &self.nodes[head_node]);
+		let scale = ((tail.latitude + head.latitude) / 2.0).to_radians().cos();
+
+		let east = (head.longitude - tail.longitude) * scale;
+		let north = head.latitude - tail.latitude;
+		east.atan2(north).to_degrees()
+	}
+
+	/// Returns the angle in degrees of the turn from the first to the second arc.
+	///
+	/// Right turns have positive, left turns negative angles.
+	/// Turning back to the previous node has an angle of 180 degrees.
+	pub fn turn_angle(&self, from_arc: usize, to_arc: usize) -> f64 {
+		let via_node = self.arc_tail(to_arc);
+		let (from_node, to_node) = (self.arc_tail(from_arc), self.arc_heads[to_arc]);
+		if from_node == to_node {
+			return 180.0;
+		}
+
+		let angle = self.arc_bearing(via_node, to_node) - self.arc_bearing(from_node, via_node);
+		if angle > 180.0 {
+			angle - 360.0
+		} else if angle <= -180.0 {
+			angle + 360.0
+		} else {
+			angle
+		}
+	}
+
+	/// Returns the tail node of the arc.
+	pub(super) fn arc_tail(&self, arc: usize) -> usize {
+		// The offsets are sorted, find the last node starting at or before the arc
+		match self.arc_offsets.binary_search(&arc) {
+			Ok(mut node) => {
+				// Skip nodes without arcs
+				while self.arc_offsets[node + 1] == arc {
+					node += 1;
+				}
+				node
+			},
+			Err(node) => node - 1
+		}
+	}
+
+	/// Compute the shortest path between two nodes with restricted and costly turns.
+	///
+	/// Runs an edge based Dijkstra search, which settles arcs instead of nodes.
+	/// The result is a tuple of the distance, the travel time with the given
+	/// max speed, the nodes on the path and the number of settled arcs. The
+	/// turn costs only influence the path, they are not part of the result.
+	pub fn compute_shortest_path_with_turns(&self, start_node: usize, end_node: usize, max_speed: u64,
+			restrictions: &TurnRestrictions, turn_costs: &TurnCosts) -> Option<(f64, f64, Box<Vec<usize>>, usize)> {
+		if start_node == end_node {
+			return Some((0.0, 0.0, Box::new(vec![start_node]), 0));
+		}
+
+		let arc_count = self.num_arcs();
+		let mut distances: Vec<Option<f64>> = vec![None; arc_count];
+		let mut settled = vec![false; arc_count];
+		let mut previous_arcs: Vec<Option<usize>> = vec![None; arc_count];
+		let mut num_settled = 0;
+
+		let mut active_arcs = BinaryHeap::<(i64, usize)>::new();
+		for arc in self.arcs(start_node) {
+			distances[arc] = Some(self.arc_costs[arc]);
+			active_arcs.push((-(self.arc_costs[arc] * 1000.0) as i64, arc));
+		}
+
+		let mut end_arc = None;
+		while let Some((_, arc)) = active_arcs.pop() {
+			if settled[arc] {
+				continue;
+			}
+
+			settled[arc] = true;
+			num_settled += 1;
+
+			let via_node = self.arc_heads[arc];
+			if via_node == end_node {
+				end_arc = Some(arc);
+				break;
+			}
+
+			let arc_distance = distances[arc].unwrap();
+			for next_arc in self.arcs(via_node) {
+				if settled[next_arc] || !restrictions.is_allowed(arc, next_arc) {
+					continue;
+				}
+
+				let u_turn = self.arc_heads[next_arc] == self.arc_tail(arc);
+				let turn_cost = match turn_costs.cost(self.turn_angle(arc, next_arc), u_turn) {
+					Some(cost) => cost,
+					None => { continue; }
+				};
+
+				let next_distance = arc_distance + turn_cost + self.arc_costs[next_arc];
+				match distances[next_arc] {
+					Some(distance) if distance <= next_distance => { continue; },
+					_ => { }
+				}
+
+				distances[next_arc] = Some(next_distance);
+				previous_arcs[next_arc] = Some(arc);
+
+				// Use negative numbers since we only have a max-heap
+				active_arcs.push((-(next_distance * 1000.0) as i64, next_arc));
+			}
+		}
+
+		let mut arc = match end_arc {
+			Some(arc) => arc,
+			None => { return None; }
+		};
+
+		let mut path = Box::new(vec![end_node]);
+		let mut distance = 0.0;
+		let mut time = 0.0;
+
+		loop {
+			distance += self.arc_distances[arc] as f64;
+			time += self.arc_travel_time(arc, max_speed);
+			path.push(self.arc_tail(arc));
+
+			match previous_arcs[arc] {
+				Some(previous_arc) => { arc = previous_arc; },
+				None => { break; }
+			}
+		}
+		path.reverse();
+
+		return Some((distance, time, path, num_settled));
+	}
+}
+
+#[test]
+fn test_turn_angle() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let arc = |tail: usize, head: usize| graph.arcs(tail).find(|&arc| graph.arc_heads[arc] == head).unwrap();
+
+	// North east, then north: a left turn
+	assert!((graph.turn_angle(arc(0, 2), arc(2, 5)) + 33.7).abs() < 0.1);
+	// North, then north east: a right turn
+	assert!((graph.turn_angle(arc(0, 3), arc(3, 5)) - 33.7).abs() < 0.1);
+	assert_eq!(180.0, graph.turn_angle(arc(0, 2), arc(2, 0)));
+	assert_eq!(6, graph.arc_tail(arc(6, 5)));
+
+	let costs = TurnCosts { free_angle: 30.0, cost_per_degree: 2.0, left_turn_factor: 3.0, allow_u_turns: false };
+	assert_eq!(Some(0.0), costs.cost(20.0, false));
+	assert_eq!(Some(20.0), costs.cost(40.0, false));
+	assert_eq!(Some(60.0), costs.cost(-40.0, false));
+	assert_eq!(None, costs.cost(180.0, true));
+}
+
+#[test]
+fn test_shortest_with_turns() {
+	let graph = Graph::read_graph_from_file("graphs/test3.zip").unwrap();
+	let unrestricted = TurnRestrictions::new();
+
+	// Same as Dijkstra without restrictions and costs
+	for start in 0..graph.num_nodes() {
+		for end in 0..graph.num_nodes() {
+			let dijkstra = graph.compute_shortest_path_dijkstra(start, end, 130).map(|path| (path.0, path.2));
+			let turns = graph.compute_shortest_path_with_turns(start, end, 130, &unrestricted, &TurnCosts::none())
+				.map(|path| (path.0, path.2));
+			assert_eq!(dijkstra, turns);
+		}
+	}
+
+	let restrictions = TurnRestrictions::read_from_file(&graph, "graphs/test3.restrictions").unwrap();
+	let path = graph.compute_shortest_path_with_turns(0, 5, 130, &restrictions, &TurnCosts::none()).unwrap();
+	assert_eq!((3700.0, vec![0, 3, 5]), (path.0, *path.2));
+
+	// Only the turn to 1 from 4 over 2, so 4 -> 0 is a detour over 1
+	let path = graph.compute_shortest_path_with_turns(4, 0, 130, &restrictions, &TurnCosts::none()).unwrap();
+	assert_eq!((3900.0, vec![4, 1, 0]), (path.0, *path.2));
+	let path = graph.compute_shortest_path_with_turns(2, 0, 130, &restrictions, &TurnCosts::none()).unwrap();
+	assert_eq!(vec![2, 0], *path.2);
+
+	// The left turn over 2 costs more than the right turn over 3
+	let costs = TurnCosts { free_angle: 0.0, cost_per_degree: 3.0, left_turn_factor: 2.0, allow_u_turns: false };
+	let path = graph.compute_shortest_path_with_turns(0, 5, 130, &unrestricted, &costs).unwrap();
+	assert_eq!((3700.0, vec![0, 3, 5]), (path.0, *path.2));
+}
\ No newline at end of file
@@ -0,0 +1,293 @@
+use super::{Error, Graph};
+use super::input;
+
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::BufRead;
+use std::str::FromStr;
+use std::vec::Vec;
+
+/// Kind of problem found in a graph file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Issue {
+	/// Line which is no valid node or arc, or missing or additional lines.
+	Malformed,
+	/// Node id which differs from the position of the node.
+	IdMismatch,
+	/// Arc with a tail or head node which does not exist.
+	UnknownNode,
+	/// Arc from a node to itself.
+	SelfLoop,
+	/// Arc with the same tail and head node as an earlier arc.
+	DuplicateArc,
+	/// Arc with a max speed of zero, its travel time is infinite.
+	ZeroSpeed
+}
+
+impl Issue {
+
+	/// Returns if a graph file with this issue cannot be read.
+	pub fn is_error(&self) -> bool {
+		match *self {
+			Issue::Malformed | Issue::UnknownNode => true,
+			_ => false
+		}
+	}
+
+	/// Returns a short description of the issue.
+	pub fn name(&self) -> &'static str {
+		match *self {
+			Issue::Malformed => "malformed line",
+			Issue::IdMismatch => "node id mismatch",
+			Issue::UnknownNode => "unknown node",
+			Issue::SelfLoop => "self-loop",
+			Issue::DuplicateArc => "duplicate arc",
+			Issue::ZeroSpeed => "zero speed"
+		}
+	}
+}
+
+/// Problem found in a line of a graph file.
+pub struct Diagnostic {
+	issue: Issue,
+	line: usize,
+	message: String
+}
+
+impl Diagnostic {
+
+	/// Returns the kind of the problem.
+	pub fn issue(&self) -> Issue {
+		self.issue
+	}
+
+	/// Returns the line number in the file, starting with one.
+	pub fn line(&self) -> usize {
+		self.line
+	}
+
+	/// Returns the details of the problem.
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+}
+
+impl Display for Diagnostic {
+	fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+		let severity = match self.issue.is_error() {
+			true => "error",
+			false => "warning"
+		};
+		write!(fmt, "line {}: {}: {} ({})", self.line, severity, self.issue.name(), self.message)
+	}
+}
+
+/// State of the validation of a single graph file entry.
+struct Validator {
+	diagnostics: Vec<Diagnostic>,
+	line_number: usize,
+	node_count: usize,
+	arc_count: usize,
+	nodes_read: usize,
+	arcs_read: usize,
+	arcs: HashSet<(usize, usize)>
+}
+
+impl Validator {
+	fn new() -> Validator {
+		Validator {
+			diagnostics: Vec::new(),
+			line_number: 0,
+			node_count: 0,
+			arc_count: 0,
+			nodes_read: 0,
+			arcs_read: 0,
+			arcs: HashSet::new()
+		}
+	}
+
+	fn report(&mut self, issue: Issue, line: usize, message: String) {
+		self.diagnostics.push(Diagnostic { issue: issue, line: line, message: message });
+	}
+
+	/// Parse the token or report the line as malformed.
+	fn parse<T: FromStr>(&mut self, token: &str, what: &str, line: usize) -> Option<T> {
+		match token.parse::<T>() {
+			Ok(value) => Some(value),
+			Err(_) => {
+				self.report(Issue::Malformed, line, format!("invalid {} \"{}\"", what, token));
+				None
+			}
+		}
+	}
+
+	/// Check a single line, the same way the graph is read.
+	fn check_line(&mut self, line: &str, total_line_number: usize) {
+		self.line_number += 1;
+		let parts: Vec<&str> = line.split(' ').collect();
+
+		match self.line_number {
+			1 => {
+				self.node_count = self.parse(parts[0], "number of nodes", total_line_number).unwrap_or(0);
+			},
+			2 => {
+				self.arc_count = self.parse(parts[0], "number of arcs", total_line_number).unwrap_or(0);
+			},
+			_ => {
+				if self.line_number < self.node_count + 3 {
+					self.check_node(&parts, total_line_number);
+				} else if self.line_number < self.node_count + self.arc_count + 3 {
+					self.check_arc(&parts, total_line_number);
+				} else {
+					self.report(Issue::Malformed, total_line_number, "additional line".to_owned());
+				}
+			}
+		}
+	}
+
+	fn check_node(&mut self, parts: &Vec<&str>, line: usize) {
+		let position = self.nodes_read;
+		self.nodes_read += 1;
+
+		if parts.len() != 3 {
+			self.report(Issue::Malformed, line, format!("node with {} instead of 3 values", parts.len()));
+			return;
+		}
+
+		let id = self.parse::<usize>(parts[0], "node id", line);
+		self.parse::<f64>(parts[1], "latitude", line);
+		self.parse::<f64>(parts[2], "longitude", line);
+
+		match id {
+			Some(id) if id != position => {
+				self.report(Issue::IdMismatch, line, format!("node {} at position {}", id, position));
+			},
+			_ => { }
+		}
+	}
+
+	fn check_arc(&mut self, parts: &Vec<&str>, line: usize) {
+		self.arcs_read += 1;
+
+		if parts.len() != 4 {
+			self.report(Issue::Malformed, line, format!("arc with {} instead of 4 values", parts.len()));
+			return;
+		}
+
+		let tail_node = self.parse::<usize>(parts[0], "tail node", line);
+		let head_node = self.parse::<usize>(parts[1], "head node", line);
+		self.parse::<u64>(parts[2], "distance", line);
+		let max_speed = self.parse::<u64>(parts[3], "max speed", line);
+
+		if max_speed == Some(0) {
+			self.report(Issue::ZeroSpeed, line, format!("arc from node {} to node {}", parts[0], parts[1]));
+		}
+
+		let (tail_node, head_node) = match (tail_node, head_node) {
+			(Some(tail_node), Some(head_node)) => (tail_node, head_node),
+			_ => { return; }
+		};
+
+		for &node in [tail_node, head_node].iter() {
+			if node >= self.nodes_read {
+				self.report(Issue::UnknownNode, line, format!("node {} of {} nodes", node, self.nodes_read));
+			}
+		}
+
+		if tail_node == head_node {
+			self.report(Issue::SelfLoop, line, format!("arc from node {} to itself", tail_node));
+		} else if !self.arcs.insert((tail_node, head_node)) {
+			self.report(Issue::DuplicateArc, line, format!("another arc from node {} to node {}", tail_node, head_node));
+		}
+	}
+
+	/// Report the lines missing at the end of the file.
+	fn finish(&mut self, total_line_number: usize) {
+		if self.line_number < 2 {
+			self.report(Issue::Malformed, total_line_number, "missing number of nodes or arcs".to_owned());
+		}
+		if self.nodes_read < self.node_count {
+			let message = format!("{} of {} nodes", self.nodes_read, self.node_count);
+			self.report(Issue::Malformed, total_line_number, message);
+		}
+		if self.arcs_read < self.arc_count {
+			let message = format!("{} of {} arcs", self.arcs_read, self.arc_count);
+			self.report(Issue::Malformed, total_line_number, message);
+		}
+	}
+}
+
+/// Check the lines of a graph file and return the problems found in order of the lines.
+fn validate_lines<R: BufRead>(buf: R) -> Result<Vec<Diagnostic>, Error> {
+	let mut validator = Validator::new();
+	let mut total_line_number = 0;
+
+	for line_res in buf.lines() {
+		let line = try!(line_res);
+		let line = line.trim();
+
+		total_line_number += 1;
+
+		if line.starts_with("#") || line.is_empty() {
+			continue;
+		}
+
+		validator.check_line(line, total_line_number);
+	}
+
+	validator.finish(total_line_number);
+	return Ok(validator.diagnostics);
+}
+
+impl Graph {
+
+	/// Check a graph file for problems without reading the graph.
+	///
+	/// Unlike reading the graph, this does not stop at the first problem but
+	/// reports all of them, including problems the graph can be read with.
+	/// The file is opened like for reading the graph.
+	pub fn validate_file(name: &str) -> Result<Vec<Diagnostic>, Error> {
+		input::with_graph_text(name, |buf| validate_lines(buf))
+	}
+}
+
+#[test]
+fn test_validate_file() {
+	for name in ["graphs/test.zip", "graphs/test2.zip", "graphs/test3.zip"].iter() {
+		assert!(Graph::validate_file(name).unwrap().is_empty());
+	}
+
+	assert!(Graph::validate_file("graphs/missing.zip").is_err());
+}
+
+#[test]
+fn test_validate_lines() {
+	let text = "# Number of nodes.\n3\n# Number of arcs.\n6\n\
+		0 48.0 7.8\n2 48.0 7.82\n2 48.01 x\n\
+		0 1 100 50\n1 1 100 50\n0 1 120 50\n1 5 100 50\n2 0 100 0\n2 1 100\n";
+	let diagnostics = validate_lines(text.as_bytes()).unwrap();
+
+	let issues: Vec<(Issue, usize)> = diagnostics.iter()
+		.map(|diagnostic| (diagnostic.issue(), diagnostic.line()))
+		.collect();
+	assert_eq!(vec![
+			(Issue::IdMismatch, 6),
+			(Issue::Malformed, 7),
+			(Issue::SelfLoop, 9),
+			(Issue::DuplicateArc, 10),
+			(Issue::UnknownNode, 11),
+			(Issue::ZeroSpeed, 12),
+			(Issue::Malformed, 13)
+		], issues);
+
+	assert_eq!("line 9: warning: self-loop (arc from node 1 to itself)", diagnostics[2].to_string());
+	assert!(diagnostics[4].issue().is_error());
+
+	// Missing and additional lines
+	let diagnostics = validate_lines("2\n0\n0 48.0 7.8\n".as_bytes()).unwrap();
+	assert_eq!(1, diagnostics.len());
+	assert_eq!("line 3: error: malformed line (1 of 2 nodes)", diagnostics[0].to_string());
+
+	let diagnostics = validate_lines("1\n0\n0 48.0 7.8\n0 0 1 1\n".as_bytes()).unwrap();
+	assert_eq!(vec![Issue::Malformed], diagnostics.iter().map(|diagnostic| diagnostic.issue()).collect::<Vec<Issue>>());
+}
\ No newline at end of file
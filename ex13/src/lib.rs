@@ -0,0 +1 @@
+pub mod graph;
\ No newline at end of file
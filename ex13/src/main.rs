@@ -1,143 +1,719 @@
-#![feature(box_syntax)]
+extern crate ex13;
+extern crate getopts;
 
-extern crate time;
+use ex13::graph::{parse_time_of_day, Capacity, ContractionHierarchy, Error, Graph, Hull, Issue, LandmarkSelection, LoadMode, Progress, Route, RouteFormat,
+	SpatialIndex, Traffic, TurnCosts, TurnRestrictions};
 
-mod graph;
-
-use graph::Graph;
-
-use std::io::Write;
-use std::u64;
+use getopts::{Matches, Options};
+use std::env;
 use std::fs::File;
-use time::get_time;
+use std::io::{self, BufWriter, Write};
+use std::thread;
+use std::time::Instant;
+use std::u64;
 
 const DEFAULT_FILE: &str = "graphs/bawue_bayern.zip";
+const DEFAULT_LANDMARKS: usize = 16;
+const FREE_TURN_ANGLE: f64 = 30.0;
+const LEFT_TURN_FACTOR: f64 = 1.5;
+const DEFAULT_INTERVAL: f64 = 15.0;
+const DEFAULT_ALTERNATIVES: usize = 3;
+const DEFAULT_OVERLAP: f64 = 0.5;
+const DEFAULT_NEIGHBOURS: usize = 10;
+const MAX_DIAGNOSTICS: usize = 20;
+
+const COMMANDS: &str = "Commands:
+    route       Compute the path from the source to the target node
+    alternatives
+                Compute alternative routes from the source to the target node
+    matrix      Compute the distances and travel times between all sources and targets
+    pareto      Compute all routes trading distance against travel time
+    tree        Compute the minimum spanning forest, or a Steiner tree connecting the sources
+    lcc         Compute the strongly connected components
+    arrival     Compute the earliest arrival at the target when departing at a time of day
+    furthest    Compute the node furthest away from the source node
+    flow        Compute the maximum flow and minimum cut between the sources and targets
+    isochrone   Compute the area reachable from the source node within the budget
+    stats       Print statistics of the graph and problems found in the graph file
+    export      Write the graph, the largest component or a bounding box of it
+
+Nodes are given as node id or as LAT,LON coordinates of the nearest node.";
+
+fn print_usage(program: &str, opts: Options) {
+	println!("{}", opts.usage(&format!("Usage: {} COMMAND [options]", program)));
+	println!("{}", COMMANDS);
+}
 
 fn main() {
-	let file_name = DEFAULT_FILE;
-
-	println!("Reading graph from file {}", &file_name);
+	let args: Vec<String> = env::args().collect();
+	let program = &args[0];
+
+	let mut opts = Options::new();
+	opts.optflag("h", "help", "Shows the help for this program.");
+	opts.optopt("g", "graph", "The graph file to read from (zip, gz, txt, osm or pbf), - reads text from stdin.", "FILE");
+	opts.optopt("", "mode", "The arcs of text graph files: directed (default), undirected or reverse.", "MODE");
+	opts.optmulti("s", "source", "The source node, for matrix, tree and flow multiple or separated by semicolons.", "NODE");
+	opts.optmulti("t", "target", "The target node, for matrix and flow multiple or separated by semicolons.", "NODE");
+	opts.optopt("m", "metric", "The arc costs: distance (default) or time.", "METRIC");
+	opts.optopt("v", "speed", "The max vehicle speed in km/h, unlimited by default.", "SPEED");
+	opts.optopt("a", "algorithm", "The routing algorithm: dijkstra (default), bidirectional, astar, alt, ch or edge, for alternatives penalty (default) or yen, for matrix dijkstra (default) or ch, for tree kruskal (default) or prim.", "ALGORITHM");
+	opts.optopt("o", "output", "The file to write the route or graph to.", "FILE");
+	opts.optopt("f", "format", "The route format: geojson, gpx, kml or map, by default from the output file.", "FORMAT");
+	opts.optopt("", "simplify", "Simplify the route with the given tolerance.", "METERS");
+	opts.optopt("", "landmarks", "The number of landmarks for alt.", "COUNT");
+	opts.optopt("", "restrictions", "The turn restrictions for edge.", "FILE");
+	opts.optopt("", "turn-cost", "The costs per degree of sharp turns for edge, disallows U-turns.", "COST");
+	opts.optopt("", "bbox", "Only export the nodes inside the bounding box.", "MINLAT,MINLON,MAXLAT,MAXLON");
+	opts.optflag("", "lcc", "Only export the largest strongly connected component.");
+	opts.optopt("", "count", "The number of alternative routes, 3 by default.", "COUNT");
+	opts.optopt("", "overlap", "The max ratio of an alternative route shared with the primary route, 0.5 by default.", "RATIO");
+	opts.optopt("", "epsilon", "Prune pareto routes within a factor of 1 + EPSILON, 0 by default.", "EPSILON");
+	opts.optopt("", "budget", "The max costs of an isochrone, in meters or seconds depending on the metric.", "COST");
+	opts.optopt("", "hull", "The isochrone boundary: convex (default) or concave.", "HULL");
+	opts.optopt("", "neighbours", "The neighbours per step of a concave hull, 10 by default.", "COUNT");
+	opts.optopt("", "threads", "The number of threads for matrix, all cores by default.", "COUNT");
+	opts.optopt("", "capacity", "The arc capacities for flow: speed (default) or vehicles per hour.", "CAPACITY");
+	opts.optopt("", "departure", "The departure time of day for arrival.", "HH:MM");
+	opts.optopt("", "until", "Compute the arrivals of all departures up to this time of day.", "HH:MM");
+	opts.optopt("", "interval", "The minutes between the departures, 15 by default.", "MINUTES");
+	opts.optopt("", "traffic", "The travel time profiles of the arcs for arrival.", "FILE");
+
+	let matches = match opts.parse(&args[1..]) {
+		Ok(m) => { m },
+		Err(e) => {
+			println!("{}", e);
+			return;
+		}
+	};
 
-	let start_time = get_time();
-	match Graph::read_graph_from_file(file_name) {
-		Ok(mut graph) => {
-			println!("Read graph with {} nodes and {} arcs in {} ms!", &graph.num_nodes(),
-					&graph.num_arcs(), (get_time() - start_time).num_milliseconds());
-			println!();
+	if matches.opt_present("h") || matches.free.len() != 1 {
+		print_usage(&program, opts);
+		return;
+	}
 
-			// --------------- LCC -----------------------------------------------------
+	let file_name = matches.opt_str("g").unwrap_or(DEFAULT_FILE.to_owned());
+	println!("Reading graph from file {}", &file_name);
 
-			//calculate_lcc(&graph);
-			println!();
+	let start_time = Instant::now();
+	let mut graph = match read_graph(&file_name, &matches) {
+		Ok(graph) => graph,
+		Err(error) => {
+			println!("{}", error);
 
-			match calculate_distances(&mut graph) {
-				Ok(_) => { },
-				Err(error) => {
+			// Show all problems of the file which can not be read
+			if matches.free[0] == "stats" && file_name != "-" {
+				if let Err(error) = run_validation(&file_name) {
 					println!("{}", error);
 				}
 			}
-		},
+			return;
+		}
+	};
+
+	println!("Read graph with {} nodes and {} arcs in {} ms!", &graph.num_nodes(),
+			&graph.num_arcs(), start_time.elapsed().as_millis());
+	println!();
+
+	let result = match matches.free[0].as_str() {
+		"route" => run_route(&mut graph, &matches),
+		"arrival" => run_arrival(&graph, &matches),
+		"alternatives" => run_alternatives(&mut graph, &matches),
+		"matrix" => run_matrix(&mut graph, &matches),
+		"pareto" => run_pareto(&graph, &matches),
+		"tree" => run_tree(&mut graph, &matches),
+		"lcc" => run_lcc(&graph),
+		"furthest" => run_furthest(&mut graph, &matches),
+		"flow" => run_flow(&graph, &matches),
+		"isochrone" => run_isochrone(&mut graph, &matches),
+		"stats" => run_stats(&graph, &file_name),
+		"export" => run_export(&graph, &matches),
+		command => Err(Error::from(format!("Unknown command {}!", command)))
+	};
+
+	match result {
+		Ok(_) => { },
 		Err(error) => {
 			println!("{}", error);
 		}
 	}
 }
 
-fn calculate_distances(graph: &mut Graph) -> std::io::Result<()> {
-	let mut nuremberg_file = File::create("nuremberg.map")?;
-	let mut longest_file = File::create("longest.map")?;
+/// Read the graph in the format given by the file extension.
+fn read_graph(file_name: &str, matches: &Matches) -> Result<Graph, Error> {
+	let mode = match matches.opt_str("mode").as_ref().map(|mode| mode.as_str()) {
+		None | Some("directed") => LoadMode::Directed,
+		Some("undirected") => LoadMode::Undirected,
+		Some("reverse") => LoadMode::Reverse,
+		Some(mode) => { return Err(Error::from(format!("Unknown mode {}!", mode))); }
+	};
+
+	// Keep the progress on a single line and end it once the graph is read
+	let mut printed = false;
+	let result = {
+		let progress = |progress: &Progress| {
+			print!("\rRead {} lines, {} of {} nodes and {} of {} arcs", progress.lines, progress.nodes,
+				progress.total_nodes, progress.arcs, progress.total_arcs);
+			io::stdout().flush().unwrap_or(());
+			printed = true;
+		};
+
+		if file_name.ends_with(".osm") || file_name.ends_with(".pbf") {
+			Graph::read_graph_from_osm_file(file_name)
+		} else if file_name == "-" {
+			// Stdin can't be cached
+			Graph::read_graph_from_file_with_progress(file_name, mode, progress)
+		} else {
+			Graph::read_graph_from_file_cached(file_name, mode, progress)
+		}
+	};
+
+	if printed {
+		println!();
+	}
+	return result;
+}
+
+fn run_route(graph: &mut Graph, matches: &Matches) -> Result<(), Error> {
+	let max_speed = try!(set_metric(graph, matches));
+
+	let mut spatial_index = None;
+	let source = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "source"))));
+	let target = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "target"))));
+
+	let algorithm = matches.opt_str("a").unwrap_or("dijkstra".to_owned());
+	let mut hierarchy = None;
+
+	match algorithm.as_str() {
+		"alt" => {
+			let count = try!(parse_option(matches, "landmarks", DEFAULT_LANDMARKS));
+
+			let start_time = Instant::now();
+			graph.select_landmarks(count, LandmarkSelection::Farthest);
+			println!("Selected {} landmarks in {} ms!", count, start_time.elapsed().as_millis());
+		},
+		"ch" => {
+			let start_time = Instant::now();
+			let contracted = ContractionHierarchy::new(graph);
+			println!("Contracted graph with {} shortcuts in {} ms!", contracted.num_shortcuts(),
+				start_time.elapsed().as_millis());
+			hierarchy = Some(contracted);
+		},
+		_ => { }
+	}
+
+	let restrictions = match matches.opt_str("restrictions") {
+		Some(file_name) => try!(TurnRestrictions::read_from_file(graph, &file_name)),
+		None => TurnRestrictions::new()
+	};
+
+	let mut turn_costs = TurnCosts::none();
+	if matches.opt_present("turn-cost") {
+		turn_costs = TurnCosts {
+			free_angle: FREE_TURN_ANGLE,
+			cost_per_degree: try!(parse_option(matches, "turn-cost", 0.0)),
+			left_turn_factor: LEFT_TURN_FACTOR,
+			allow_u_turns: false
+		};
+	}
+
+	let start_time = Instant::now();
+	let path = match algorithm.as_str() {
+		"dijkstra" => graph.compute_shortest_path_dijkstra(source, target, max_speed),
+		"astar" => graph.compute_shortest_path_astar(source, target, max_speed),
+		"alt" => graph.compute_shortest_path_alt(source, target, max_speed),
+		"ch" => hierarchy.unwrap().compute_shortest_path(graph, source, target, max_speed),
+		"edge" => graph.compute_shortest_path_with_turns(source, target, max_speed, &restrictions, &turn_costs),
+		"bidirectional" => {
+			graph.compute_shortest_path_bidirectional(source, target, max_speed)
+				.map(|(distance, time, path)| (distance, time, path, 0))
+		},
+		_ => { return Err(Error::from(format!("Unknown algorithm {}!", algorithm))); }
+	};
+	let time = start_time.elapsed().as_millis();
+
+	match path {
+		Some((distance, travel_time, path, num_settled)) => {
+			println!("Distance {:.2} km, Time {}, Computation time {} ms, Settled nodes {}",
+				distance / 1000.0, time_to_string(travel_time), time, num_settled);
+
+			if matches.opt_present("o") {
+				let route = try!(graph.generate_route(&path, max_speed));
+				try!(write_route(route, matches));
+			}
+		},
+		None => {
+			println!("No path found from {} to {}!", source, target);
+		}
+	}
+
+	Ok(())
+}
+
+fn run_arrival(graph: &Graph, matches: &Matches) -> Result<(), Error> {
+	let max_speed = try!(parse_option(matches, "speed", u64::MAX));
+
+	let mut spatial_index = None;
+	let source = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "source"))));
+	let target = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "target"))));
+	let departure = try!(parse_time(try!(required(matches, "departure"))));
+
+	let traffic = match matches.opt_str("traffic") {
+		Some(file_name) => try!(Traffic::read_from_file(graph, &file_name, max_speed)),
+		None => Traffic::new(graph, max_speed)
+	};
+
+	if let Some(until) = matches.opt_str("until") {
+		let last_departure = try!(parse_time(until));
+		let interval = try!(parse_option(matches, "interval", DEFAULT_INTERVAL)) * 60.0;
+
+		let start_time = Instant::now();
+		let profile = try!(graph.compute_earliest_arrival_profile(source, target, departure, last_departure, interval, &traffic));
+		let time = start_time.elapsed().as_millis();
+
+		match profile {
+			Some(profile) => {
+				for (departure, arrival) in profile.into_iter() {
+					println!("Departure {}, Arrival {}, Time {}", time_of_day_to_string(departure),
+						time_of_day_to_string(arrival), time_to_string(arrival - departure));
+				}
+				println!("Computation time {} ms", time);
+			},
+			None => {
+				println!("No path found from {} to {}!", source, target);
+			}
+		}
+
+		return Ok(());
+	}
+
+	let start_time = Instant::now();
+	let path = graph.compute_earliest_arrival(source, target, departure, &traffic);
+	let time = start_time.elapsed().as_millis();
+
+	match path {
+		Some((arrival, path, num_settled)) => {
+			println!("Arrival {}, Time {}, Computation time {} ms, Settled nodes {}",
+				time_of_day_to_string(arrival), time_to_string(arrival - departure), time, num_settled);
+
+			if matches.opt_present("o") {
+				let route = try!(graph.generate_route(&path, max_speed));
+				try!(write_route(route, matches));
+			}
+		},
+		None => {
+			println!("No path found from {} to {}!", source, target);
+		}
+	}
+
+	Ok(())
+}
+
+fn run_alternatives(graph: &mut Graph, matches: &Matches) -> Result<(), Error> {
+	let max_speed = try!(set_metric(graph, matches));
+	let count = try!(parse_option(matches, "count", DEFAULT_ALTERNATIVES));
+	let max_overlap = try!(parse_option(matches, "overlap", DEFAULT_OVERLAP));
+
+	let mut spatial_index = None;
+	let source = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "source"))));
+	let target = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "target"))));
+
+	let algorithm = matches.opt_str("a").unwrap_or("penalty".to_owned());
+
+	let start_time = Instant::now();
+	let paths = match algorithm.as_str() {
+		"penalty" => graph.compute_alternative_paths(source, target, count, max_overlap, max_speed),
+		"yen" => graph.compute_k_shortest_paths(source, target, count, max_speed),
+		_ => { return Err(Error::from(format!("Unknown algorithm {}!", algorithm))); }
+	};
+	let time = start_time.elapsed().as_millis();
+
+	if paths.is_empty() {
+		println!("No path found from {} to {}!", source, target);
+		return Ok(());
+	}
+
+	for &(distance, travel_time, ref path) in paths.iter() {
+		println!("Distance {:.2} km, Time {}, Nodes {}", distance / 1000.0, time_to_string(travel_time), path.len());
+	}
+	println!("Found {} routes in {} ms", paths.len(), time);
+
+	Ok(())
+}
+
+fn run_matrix(graph: &mut Graph, matches: &Matches) -> Result<(), Error> {
+	let max_speed = try!(set_metric(graph, matches));
+
+	let mut spatial_index = None;
+	let nodes = [try!(parse_nodes(graph, &mut spatial_index, matches, "source")),
+		try!(parse_nodes(graph, &mut spatial_index, matches, "target"))];
+
+	let algorithm = matches.opt_str("a").unwrap_or("dijkstra".to_owned());
+	let threads = try!(parse_option(matches, "threads",
+		thread::available_parallelism().map(|count| count.get()).unwrap_or(1)));
+
+	let start_time = Instant::now();
+	let matrix = match algorithm.as_str() {
+		"dijkstra" => graph.compute_distance_matrix(&nodes[0], &nodes[1], max_speed, threads),
+		"ch" => ContractionHierarchy::new(graph).compute_distance_matrix(graph, &nodes[0], &nodes[1], max_speed),
+		_ => { return Err(Error::from(format!("Unknown algorithm {}!", algorithm))); }
+	};
+	let time = start_time.elapsed().as_millis();
+	println!("Computed {}x{} matrix in {} ms", nodes[0].len(), nodes[1].len(), time);
+
+	match matches.opt_str("o") {
+		Some(output) => {
+			let mut out = BufWriter::new(try!(File::create(&output)));
+			try!(matrix.write_csv(graph, &mut out));
+			try!(out.flush());
+
+			println!("Wrote matrix to {}", output);
+		},
+		None => {
+			let stdout = io::stdout();
+			try!(matrix.write_csv(graph, &mut stdout.lock()));
+		}
+	}
+
+	Ok(())
+}
+
+fn run_tree(graph: &mut Graph, matches: &Matches) -> Result<(), Error> {
+	try!(set_metric(graph, matches));
 
-	nuremberg_file.write_all(b"[map]")?;
-	longest_file.write_all(b"[map]")?;
+	let mut spatial_index = None;
+	let terminals = try!(parse_nodes(graph, &mut spatial_index, matches, "source"));
+	let algorithm = matches.opt_str("a").unwrap_or("kruskal".to_owned());
 
-	// ------------------------ Distance ------------------------------------
+	let start_time = Instant::now();
+	let tree = match (terminals.is_empty(), algorithm.as_str()) {
+		(false, _) => {
+			match graph.compute_steiner_tree(&terminals) {
+				Some(tree) => tree,
+				None => { return Err(Error::from("The sources are not connected!")); }
+			}
+		},
+		(true, "kruskal") => graph.compute_spanning_forest_kruskal(),
+		(true, "prim") => graph.compute_spanning_forest_prim(),
+		_ => { return Err(Error::from(format!("Unknown algorithm {}!", algorithm))); }
+	};
+	let time = start_time.elapsed().as_millis();
+
+	println!("Computed tree with {} nodes and {} arcs in {} ms", tree.nodes().len(), tree.arcs().len(), time);
+	println!("Trees: {}", tree.roots().len());
+	println!("Costs: {}", tree.costs());
+
+	if let Some(output) = matches.opt_str("o") {
+		let mut mapbb = String::new();
+		tree.generate_mapbb(graph, &mut mapbb);
+
+		let mut out = BufWriter::new(try!(File::create(&output)));
+		try!(out.write_all(mapbb.as_bytes()));
+		try!(out.flush());
 
-	let path = calculate_path(graph, u64::MAX);
+		println!("Wrote {} branches to {}", mapbb.lines().count(), output);
+	}
+
+	Ok(())
+}
 
-	nuremberg_file.write_all(path.0.as_bytes())?;
-	nuremberg_file.write_all(b" (blue|distance)[/map]\r\n[map]")?;
+fn run_flow(graph: &Graph, matches: &Matches) -> Result<(), Error> {
+	let mut spatial_index = None;
+	let sources = try!(parse_nodes(graph, &mut spatial_index, matches, "source"));
+	let sinks = try!(parse_nodes(graph, &mut spatial_index, matches, "target"));
 
-	longest_file.write_all(path.1.as_bytes())?;
-	longest_file.write_all(b" (blue|distance)[/map]\r\n[map]")?;
+	let capacity = match matches.opt_str("capacity").as_ref().map(|capacity| capacity.as_str()) {
+		None | Some("speed") => Capacity::MaxSpeed,
+		Some("vehicles") => Capacity::VehiclesPerHour,
+		Some(capacity) => { return Err(Error::from(format!("Unknown capacity {}!", capacity))); }
+	};
 
-	// ---------------- Time with 130 km/h ----------------------------------
+	let start_time = Instant::now();
+	let flow = match graph.compute_max_flow(&sources, &sinks, capacity) {
+		Some(flow) => flow,
+		None => { return Err(Error::from("A node is both source and target!")); }
+	};
+	let time = start_time.elapsed().as_millis();
 
-	let path = calculate_path(graph, 130);
+	println!("Computed maximum flow of {} in {} ms", flow.flow(), time);
+	println!("Minimum cut with {} arcs:", flow.cut_arcs().len());
+	for &arc in flow.cut_arcs().iter() {
+		let (tail_node, head_node) = graph.arc_nodes(arc);
+		println!("{} -> {}: {}", tail_node, head_node, flow.arc_flow(arc));
+	}
 
-	nuremberg_file.write_all(path.0.as_bytes())?;
-	nuremberg_file.write_all(b" (red|130kmh)[/map]\r\n[map]")?;
+	Ok(())
+}
 
-	longest_file.write_all(path.1.as_bytes())?;
-	longest_file.write_all(b" (red|130kmh)[/map]\r\n[map]")?;
+fn run_pareto(graph: &Graph, matches: &Matches) -> Result<(), Error> {
+	let max_speed = try!(parse_option(matches, "speed", u64::MAX));
+	let epsilon = try!(parse_option(matches, "epsilon", 0.0));
 
-	// ---------------- Time with 100 km/h ----------------------------------
+	let mut spatial_index = None;
+	let source = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "source"))));
+	let target = try!(parse_node(graph, &mut spatial_index, try!(required(matches, "target"))));
 
-	let path = calculate_path(graph, 100);
+	let start_time = Instant::now();
+	let paths = graph.compute_pareto_paths(source, target, max_speed, epsilon);
+	let time = start_time.elapsed().as_millis();
 
-	nuremberg_file.write_all(path.0.as_bytes())?;
-	nuremberg_file.write_all(b" (green|100kmh)[/map]")?;
+	if paths.is_empty() {
+		println!("No path found from {} to {}!", source, target);
+		return Ok(());
+	}
 
-	longest_file.write_all(path.1.as_bytes())?;
-	longest_file.write_all(b" (green|100kmh)[/map]")?;
+	for &(distance, travel_time, ref path) in paths.iter() {
+		println!("Distance {:.2} km, Time {}, Nodes {}", distance / 1000.0, time_to_string(travel_time), path.len());
+	}
+	println!("Found {} routes in {} ms", paths.len(), time);
 
-	return Ok(());
+	Ok(())
 }
 
-fn calculate_lcc(graph: &Graph) {
-	let start_time = get_time();
+fn run_lcc(graph: &Graph) -> Result<(), Error> {
+	let start_time = Instant::now();
 	let lcc = graph.compute_lcc();
-	let time = (get_time() - start_time).num_milliseconds();
+	let time = start_time.elapsed().as_millis();
 	println!("Calculated lcc with size {} in {} ms!", &lcc.0, time);
+
+	Ok(())
+}
+
+fn run_furthest(graph: &mut Graph, matches: &Matches) -> Result<(), Error> {
+	let max_speed = try!(set_metric(graph, matches));
+	let source = try!(parse_node(graph, &mut None, try!(required(matches, "source"))));
+
+	let start_time = Instant::now();
+	let paths = graph.compute_shortest_paths(source);
+	let furthest_node = paths.get_furthest_node();
+	let path = graph.travel_to(&paths, furthest_node.1, max_speed);
+	let time = start_time.elapsed().as_millis();
+
+	println!("Furthest node {}: Distance {:.2} km, Time {}, Computation time {} ms",
+		furthest_node.1, path.0 / 1000.0, time_to_string(path.1), time);
+
+	if matches.opt_present("o") {
+		let route = graph.generate_route_to(&paths, furthest_node.1, max_speed);
+		try!(write_route(route, matches));
+	}
+
+	Ok(())
+}
+
+fn run_isochrone(graph: &mut Graph, matches: &Matches) -> Result<(), Error> {
+	try!(set_metric(graph, matches));
+	let source = try!(parse_node(graph, &mut None, try!(required(matches, "source"))));
+	let max_cost = try!(parse_option(matches, "budget", 0.0));
+
+	let hull = match matches.opt_str("hull").as_ref().map(|hull| hull.as_str()) {
+		None | Some("convex") => Hull::Convex,
+		Some("concave") => Hull::Concave(try!(parse_option(matches, "neighbours", DEFAULT_NEIGHBOURS))),
+		Some(hull) => { return Err(Error::from(format!("Unknown hull {}!", hull))); }
+	};
+
+	let start_time = Instant::now();
+	let isochrone = graph.compute_isochrone(source, max_cost, hull);
+	let time = start_time.elapsed().as_millis();
+
+	println!("Reachable nodes {}, Boundary points {}, Computation time {} ms",
+		isochrone.nodes().len(), isochrone.boundary().len(), time);
+
+	if let Some(output) = matches.opt_str("o") {
+		try!(isochrone.write_to_file(&output, try!(output_format(matches))));
+		println!("Wrote isochrone to {}", output);
+	}
+
+	Ok(())
+}
+
+fn run_stats(graph: &Graph, file_name: &str) -> Result<(), Error> {
+	print!("{}", graph.compute_statistics());
+
+	// Problems are only found in graph files, not in OSM data, and stdin was already read
+	if file_name.ends_with(".osm") || file_name.ends_with(".pbf") || file_name == "-" {
+		return Ok(());
+	}
+
+	println!();
+	run_validation(file_name)
+}
+
+/// Print the number of problems of each kind in the graph file and the first problems.
+fn run_validation(file_name: &str) -> Result<(), Error> {
+	let diagnostics = try!(Graph::validate_file(file_name));
+	if diagnostics.is_empty() {
+		println!("No problems found in {}", file_name);
+		return Ok(());
+	}
+
+	let mut issues: Vec<Issue> = diagnostics.iter().map(|diagnostic| diagnostic.issue()).collect();
+	issues.sort();
+	issues.dedup();
+
+	println!("Found {} problems in {}:", diagnostics.len(), file_name);
+	for &issue in issues.iter() {
+		let count = diagnostics.iter().filter(|diagnostic| diagnostic.issue() == issue).count();
+		println!("  {}: {}", issue.name(), count);
+	}
+
+	for diagnostic in diagnostics.iter().take(MAX_DIAGNOSTICS) {
+		println!("{}", diagnostic);
+	}
+	if diagnostics.len() > MAX_DIAGNOSTICS {
+		println!("... and {} more", diagnostics.len() - MAX_DIAGNOSTICS);
+	}
+
+	Ok(())
 }
 
-fn calculate_path(graph: &mut Graph, max_speed: u64) -> (String, String) {
-	graph.reset();
+fn run_export(graph: &Graph, matches: &Matches) -> Result<(), Error> {
+	let output = try!(required(matches, "output"));
 
-	match max_speed {
-		u64::MAX => {
+	let mut exported = None;
+	if matches.opt_present("lcc") {
+		exported = Some(graph.extract_largest_scc().0);
+	}
+
+	if let Some(bbox) = matches.opt_str("bbox") {
+		let values = try!(parse_numbers(&bbox));
+		if values.len() != 4 {
+			return Err(Error::from(format!("Invalid bounding box {}!", bbox)));
+		}
+
+		let source = exported.as_ref().unwrap_or(graph);
+		exported = Some(source.extract_bounding_box(values[0], values[1], values[2], values[3]).0);
+	}
+
+	let exported = exported.as_ref().unwrap_or(graph);
+	try!(exported.write_graph_to_file(&output));
+	println!("Wrote graph with {} nodes and {} arcs to {}", exported.num_nodes(), exported.num_arcs(), output);
+
+	Ok(())
+}
+
+/// Set the arc costs to the metric and return the max vehicle speed.
+fn set_metric(graph: &mut Graph, matches: &Matches) -> Result<u64, Error> {
+	let max_speed = try!(parse_option(matches, "speed", u64::MAX));
+
+	match matches.opt_str("m").as_ref().map(|metric| metric.as_str()) {
+		None | Some("distance") => {
 			graph.set_arc_costs_to_distance();
 		},
-		_ => {
+		Some("time") => {
 			graph.set_arc_costs_to_travel_time(max_speed);
+		},
+		Some(metric) => {
+			return Err(Error::from(format!("Unknown metric {}!", metric)));
 		}
 	}
 
-	let (mut nuremberg, mut longest) = (String::default(), String::default());
+	Ok(max_speed)
+}
 
-	// Shortest / Fastest
+/// Parse a node id or the coordinates of the nearest node.
+fn parse_node(graph: &Graph, spatial_index: &mut Option<SpatialIndex>, value: String) -> Result<usize, Error> {
+	if !value.contains(',') {
+		let id = try!(value.parse::<usize>());
+		return match graph.node_with_id(id) {
+			Some(node) => Ok(node),
+			None => Err(Error::from(format!("Unknown node id {}!", id)))
+		};
+	}
 
-	let start_time = get_time();
-	graph.compute_shortest_paths(5508637);
-	let path = graph.travel_to(4435496, max_speed);
-	let time = (get_time() - start_time).num_milliseconds();
+	let coordinates = try!(parse_numbers(&value));
+	if coordinates.len() != 2 {
+		return Err(Error::from(format!("Invalid coordinates {}!", value)));
+	}
 
-	match max_speed {
-		u64::MAX => { println!("Shortest path:") }
-		_ => { println!("Fastest path with {} km/h:", max_speed) }
-	};
-	println!("Distance {:.2} km, Time {}, Computation time {} ms",	path.0 / 1000.0,
-		time_to_string(path.1), time);
+	if spatial_index.is_none() {
+		*spatial_index = Some(SpatialIndex::new(graph));
+	}
 
-	graph.generate_mapbb(&mut nuremberg, 4435496);
+	match spatial_index.as_ref().unwrap().nearest(coordinates[0], coordinates[1]) {
+		Some((node, distance)) => {
+			println!("Using node {} at {:.0} m distance for {}", node, distance, value);
+			Ok(node)
+		},
+		None => Err(Error::from("The graph has no nodes!"))
+	}
+}
 
-	// Longest
+fn parse_numbers(value: &str) -> Result<Vec<f64>, Error> {
+	let mut numbers = Vec::new();
+	for part in value.split(',') {
+		numbers.push(try!(part.trim().parse::<f64>()));
+	}
 
-	let start_time = get_time();
-	let furthest_node = graph.get_furthest_node();
-	let path = graph.travel_to(furthest_node.1, max_speed);
-	let time = (get_time() - start_time).num_milliseconds();
+	Ok(numbers)
+}
 
-	match max_speed {
-		u64::MAX => { println!("Longest path:") }
-		_ => { println!("Longest path with {} km/h:", max_speed) }
-	};
-	println!("Distance {:.2} km, Time {}, Computation time {} ms",	path.0 / 1000.0,
-		time_to_string(path.1), time);
-	println!();
+fn parse_option<T: std::str::FromStr>(matches: &Matches, name: &str, default: T) -> Result<T, Error> {
+	match matches.opt_str(name) {
+		Some(value) => {
+			match value.parse::<T>() {
+				Ok(value) => Ok(value),
+				Err(_) => Err(Error::from(format!("Invalid value {} for option {}!", value, name)))
+			}
+		},
+		None => Ok(default)
+	}
+}
+
+fn parse_time(value: String) -> Result<f64, Error> {
+	match parse_time_of_day(&value) {
+		Some(time) => Ok(time),
+		None => Err(Error::from(format!("Invalid time of day {}!", value)))
+	}
+}
+
+/// Parse all nodes of the option, the values may contain multiple nodes separated by semicolons.
+fn parse_nodes(graph: &Graph, spatial_index: &mut Option<SpatialIndex>, matches: &Matches,
+		name: &str) -> Result<Vec<usize>, Error> {
+	let mut nodes = Vec::new();
+	for value in matches.opt_strs(name).iter() {
+		for node in value.split(';').filter(|node| !node.is_empty()) {
+			nodes.push(try!(parse_node(graph, spatial_index, node.to_owned())));
+		}
+	}
+
+	Ok(nodes)
+}
+
+fn required(matches: &Matches, name: &str) -> Result<String, Error> {
+	match matches.opt_str(name) {
+		Some(value) => Ok(value),
+		None => Err(Error::from(format!("Missing option {}!", name)))
+	}
+}
+
+/// Returns the format option, without it the format is given by the extension of the output file.
+fn output_format(matches: &Matches) -> Result<Option<RouteFormat>, Error> {
+	match matches.opt_str("f") {
+		Some(format) => {
+			match format.as_str() {
+				"geojson" => Ok(Some(RouteFormat::GeoJson)),
+				"gpx" => Ok(Some(RouteFormat::Gpx)),
+				"kml" => Ok(Some(RouteFormat::Kml)),
+				"map" => Ok(Some(RouteFormat::Mapbb)),
+				_ => Err(Error::from(format!("Unknown format {}!", format)))
+			}
+		},
+		None => Ok(None)
+	}
+}
+
+/// Simplify and write the route to the output file.
+fn write_route(mut route: Route, matches: &Matches) -> Result<(), Error> {
+	let output = try!(required(matches, "output"));
+	let format = try!(output_format(matches));
+
+	if matches.opt_present("simplify") {
+		route.simplify(try!(parse_option(matches, "simplify", 0.0)));
+	}
 
-	graph.generate_mapbb(&mut longest, furthest_node.1);
+	try!(route.write_to_file(&output, format));
 
-	return (nuremberg, longest);
+	println!("Wrote route to {}", output);
+	Ok(())
 }
 
 /// Convert time in hours to string format.
@@ -145,4 +721,9 @@ fn time_to_string(time: f64) -> String {
     let hours = (time / 3600.0) as u64;
     let minutes = ((time - (hours as f64) * 3600.0) / 60.0) as u64;
     format!("{} hour(s) {} minute(s)", hours, minutes)
+}
+
+fn time_of_day_to_string(time: f64) -> String {
+	let seconds = time.round() as u64;
+	format!("{:02}:{:02}:{:02}", (seconds / 3600) % 24, (seconds / 60) % 60, seconds % 60)
 }
\ No newline at end of file